@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use tauri::{Emitter, State};
+
+use crate::{
+    core::{app_handle::app_handle, errors::AppError, history_store::SharedHistoryStore},
+    structures::{
+        archive::{ImportConflict, ImportReport},
+        entry_filters::EntryFilters,
+    },
+};
+
+#[tauri::command]
+pub async fn export_history(
+    store: State<'_, SharedHistoryStore>,
+    path: String,
+    filters: Option<EntryFilters>,
+) -> Result<usize, AppError> {
+    // The whole history (the trash excluded) without filters
+    Ok(store
+        .export(&PathBuf::from(path), filters.unwrap_or_default())
+        .await?)
+}
+
+#[tauri::command]
+pub async fn import_history(
+    store: State<'_, SharedHistoryStore>,
+    path: String,
+    conflict: Option<ImportConflict>,
+) -> Result<ImportReport, AppError> {
+    let report = store
+        .import(&PathBuf::from(path), conflict.unwrap_or_default())
+        .await?;
+
+    // New or updated entries have to be displayed
+    if report.imported + report.overwritten + report.merged > 0 {
+        app_handle().emit("reload-window", {})?;
+    }
+
+    Ok(report)
+}
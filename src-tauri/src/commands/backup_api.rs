@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use tauri::{Emitter, State};
+
+use crate::{
+    core::{
+        app_handle::app_handle, errors::AppError, profiles::ProfileStore,
+        tasks::clipboard_watcher::suspend_capture,
+    },
+    structures::{backup::Backup, config::config},
+};
+
+#[tauri::command]
+pub async fn list_backups(store: State<'_, Arc<ProfileStore>>) -> Result<Vec<Backup>, AppError> {
+    Ok(store.current().list_backups())
+}
+
+#[tauri::command]
+pub async fn restore_backup(
+    store: State<'_, Arc<ProfileStore>>,
+    id: String,
+) -> Result<(), AppError> {
+    // Pause the watcher so no entry is captured while the database is replaced
+    let suspension = suspend_capture();
+    let restored = store.current().restore_backup(config(), id).await;
+    drop(suspension);
+    restored?;
+
+    // The window still shows the previous history
+    app_handle().emit("reload-window", {})?;
+
+    Ok(())
+}
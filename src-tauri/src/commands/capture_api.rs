@@ -0,0 +1,36 @@
+use crate::{
+    core::{
+        app_handle::app_handle,
+        capture_state::{self, capture_state, publish_capture_state},
+        errors::{AppError, DatabaseError},
+    },
+    structures::capture_state::CaptureState,
+};
+
+#[tauri::command]
+pub async fn pause_capture(minutes: Option<u64>) -> Result<CaptureState, AppError> {
+    if minutes == Some(0) {
+        return Err(
+            DatabaseError::InvalidInput("The pause must last at least a minute".into()).into(),
+        );
+    }
+
+    // Without a duration, the capture stays paused until resumed
+    capture_state::pause_capture(minutes);
+    publish_capture_state(app_handle());
+
+    Ok(capture_state())
+}
+
+#[tauri::command]
+pub async fn resume_capture() -> Result<CaptureState, AppError> {
+    capture_state::resume_capture();
+    publish_capture_state(app_handle());
+
+    Ok(capture_state())
+}
+
+#[tauri::command]
+pub async fn get_capture_state() -> Result<CaptureState, AppError> {
+    Ok(capture_state())
+}
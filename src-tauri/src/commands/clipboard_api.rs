@@ -1,52 +1,150 @@
+use std::collections::HashMap;
+
 use clipboard_rs::{Clipboard, ClipboardContext};
 use enigo::{
     Direction::{Click, Press, Release},
     Enigo, Key, Keyboard, Settings,
 };
-use tauri::{Listener, Manager};
+use tauri::{Emitter, Listener, Manager, State};
 
 use crate::{
     commands::toggle_window::toggle_window,
     core::{
         app_handle::app_handle,
-        database_api::{delete_item_by_id, get_all_ids, get_item_by_id, get_truncated_item_by_id},
-        tasks::clipboard_watcher::{LAST_IMAGE, LAST_TEXT},
+        errors::AppError,
+        history_store::SharedHistoryStore,
+        tasks::clipboard_watcher::{image_fingerprint, LAST_IMAGE, LAST_TEXT},
+        templates::{Template, TemplateValues},
     },
     structures::{
         clipboard_entry::ClipboardEntry,
         config::{config, AppConfig},
+        entry_filters::EntryFilters,
+        entry_page::{EntryPage, EntrySort, ListCursor},
     },
 };
 
 #[tauri::command]
-pub fn get_clipboard_entries_ids() -> Vec<i64> {
-    // Get all clipboard entry IDs from the database API
-    return get_all_ids();
+pub async fn get_clipboard_entries_ids(
+    store: State<'_, SharedHistoryStore>,
+) -> Result<Vec<i64>, AppError> {
+    // Get all clipboard entry IDs from the history
+    Ok(store.ids().await?)
 }
 
 #[tauri::command]
-pub fn get_clipboard_entry(id: i64) -> ClipboardEntry {
-    // Get the clipboard entry from the database API using the provided ID
-    return get_truncated_item_by_id(id).unwrap();
+pub async fn get_clipboard_entry(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+) -> Result<ClipboardEntry, AppError> {
+    let configuration: AppConfig = config();
+
+    // Get the preview of the clipboard entry from the history using the provided ID
+    let max_characters = configuration.max_displayed_characters.max(0) as usize;
+    Ok(store.preview(id, max_characters).await?)
 }
 
-// Function to push a clipboard entry to the clipboard
+// Default number of entries in a page of the history
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+
 #[tauri::command]
-pub fn push_to_clipboard(id: i64) {
-    // Get the clipboard from the app handle
-    let app = app_handle();
-    let clipboard = app.state::<tauri_plugin_clipboard::Clipboard>();
+pub async fn list_entries(
+    store: State<'_, SharedHistoryStore>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+    sort: Option<EntrySort>,
+    filters: Option<EntryFilters>,
+) -> Result<EntryPage, AppError> {
+    // An invalid cursor restarts from the first page
+    let cursor: Option<ListCursor> = cursor.and_then(|c| serde_json::from_str(&c).ok());
 
+    // Get a page of previews from the history
+    let configuration: AppConfig = config();
+    let page = store
+        .list(
+            cursor,
+            limit.unwrap_or(DEFAULT_PAGE_SIZE),
+            sort.unwrap_or_default(),
+            filters.unwrap_or_default(),
+            configuration.max_displayed_characters.max(0) as usize,
+        )
+        .await?;
+
+    Ok(page)
+}
+
+// Function to expand the placeholders of a template
+// Fails with InputRequired (listing the names to ask the user) until every input has a value
+async fn expand_template(
+    store: &SharedHistoryStore,
+    content: &str,
+    inputs: HashMap<String, String>,
+) -> Result<String, AppError> {
+    let template = Template::parse(content)?;
+
+    let missing = template.missing_inputs(&inputs);
+    if !missing.is_empty() {
+        return Err(AppError::InputRequired(missing));
+    }
+
+    // Referenced entries are inserted as they are, images can't be inserted in text
+    let mut entries = HashMap::new();
+    for id in template.entry_ids() {
+        if let Ok(entry) = store.get(id).await {
+            if entry.entry_type != "image" {
+                entries.insert(id, entry.content);
+            }
+        }
+    }
+
+    // Read before the clipboard is overwritten by the expanded template
+    let clipboard = if template.uses_clipboard() {
+        let app = app_handle();
+        let clipboard = app.state::<tauri_plugin_clipboard::Clipboard>();
+        clipboard.read_text().ok()
+    } else {
+        None
+    };
+
+    Ok(template.expand(&TemplateValues {
+        clipboard,
+        entries,
+        inputs,
+    })?)
+}
+
+// Function to push a clipboard entry to the clipboard
+// `inputs` holds the values of the {input:NAME} placeholders of a template
+#[tauri::command]
+pub async fn push_to_clipboard(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+    inputs: Option<HashMap<String, String>>,
+) -> Result<(), AppError> {
     // Get the configuration
     let configuration: AppConfig = config();
 
-    // Get the full entry from the database
-    let entry = get_item_by_id(id).unwrap();
+    // Get the full entry from the history
+    let mut entry = store.get(id).await?;
 
-    // If the clipboard history shouldn't be rewritten, set the current clipboard state to avoid duplicates
-    if !configuration.window_rewrite_history_on_copy {
+    // Templates are expanded first, nothing is copied if a placeholder can't be resolved
+    if entry.is_template {
+        entry.content = expand_template(&store, &entry.content, inputs.unwrap_or_default()).await?;
+    }
+
+    // Keep track of the usage of the entry, moved to the top of the history if it should be rewritten (templates are kept in place)
+    let move_to_top = configuration.window_rewrite_history_on_copy && !entry.is_template;
+    store.record_usage(id, move_to_top).await?;
+
+    // Get the clipboard from the app handle
+    let app = app_handle();
+    let clipboard = app.state::<tauri_plugin_clipboard::Clipboard>();
+
+    // Set the current clipboard state so the watcher doesn't capture the entry again
+    // The expansion of a template is captured as a new entry when the history should be rewritten
+    if !configuration.window_rewrite_history_on_copy || !entry.is_template {
         if entry.entry_type == "image" {
-            LAST_IMAGE.lock().unwrap().clone_from(&entry.content);
+            *LAST_IMAGE.lock().unwrap() = image_fingerprint(&entry.content);
         } else {
             LAST_TEXT.lock().unwrap().clone_from(&entry.content);
         }
@@ -54,7 +152,9 @@ pub fn push_to_clipboard(id: i64) {
 
     // If the entry is an image, write it to the clipboard as binary
     if entry.entry_type == "image" {
-        let _ = clipboard.write_image_base64(entry.content);
+        clipboard
+            .write_image_base64(entry.content)
+            .map_err(AppError::Clipboard)?;
     } else if entry.entry_type == "rich_text" {
         // If the entry is rich text, write it as RTF
         if let Ok(json) = serde_json::from_str::<serde_json::Value>(&entry.content) {
@@ -65,15 +165,16 @@ pub fn push_to_clipboard(id: i64) {
             #[cfg(target_os = "windows")]
             {
                 // Open the clipboard
-                let clip = ClipboardContext::new().expect("Failed to open clipboard");
+                let clip =
+                    ClipboardContext::new().map_err(|e| AppError::Clipboard(e.to_string()))?;
 
                 // Send the plain text
                 clip.set_text(plain)
-                    .expect("Failed to write plain text to clipboard");
+                    .map_err(|e| AppError::Clipboard(e.to_string()))?;
 
                 // Send the RTF data
                 clip.set_rich_text(rtf)
-                    .expect("Failed to write RTF to clipboard");
+                    .map_err(|e| AppError::Clipboard(e.to_string()))?;
 
                 println!("RTF data written to clipboard.");
             }
@@ -81,21 +182,28 @@ pub fn push_to_clipboard(id: i64) {
             // On macOS and Linux, write the RTF to the clipboard
             #[cfg(not(target_os = "windows"))]
             {
-                let _ = clipboard.write_rtf(rtf);
+                clipboard.write_rtf(rtf).map_err(AppError::Clipboard)?;
             }
         } else {
             // If the RTF extraction fails, write it as text
-            let _ = clipboard.write_text(entry.content);
+            clipboard
+                .write_text(entry.content)
+                .map_err(AppError::Clipboard)?;
         }
     } else {
         // Otherwise, write it as text
-        let _ = clipboard.write_text(entry.content);
+        clipboard
+            .write_text(entry.content)
+            .map_err(AppError::Clipboard)?;
     }
 
-    // If history should be rewritten, remove the old entry from the database
-    if configuration.window_rewrite_history_on_copy {
-        let _ = delete_item_by_id(id);
-        println!("Deleted entry with ID: {}", id);
+    // If history should be rewritten, show the entry at the top of the history
+    if move_to_top {
+        let _ = app.emit("delete-item", id);
+        let max_characters = configuration.max_displayed_characters.max(0) as usize;
+        if let Ok(row) = store.preview(id, max_characters).await {
+            let _ = app.emit("new-clipboard-item", row);
+        }
     }
 
     // If auto closing is enabled, close the window after a 700ms delay
@@ -119,7 +227,7 @@ pub fn push_to_clipboard(id: i64) {
         }
     });
 
-    return;
+    Ok(())
 }
 
 // Handler function to paste the content
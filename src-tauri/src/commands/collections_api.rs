@@ -0,0 +1,85 @@
+use tauri::State;
+
+use crate::{
+    core::{errors::AppError, history_store::SharedHistoryStore},
+    structures::collection::Collection,
+};
+
+#[tauri::command]
+pub async fn list_collections(
+    store: State<'_, SharedHistoryStore>,
+) -> Result<Vec<Collection>, AppError> {
+    Ok(store.list_collections().await?)
+}
+
+#[tauri::command]
+pub async fn create_collection(
+    store: State<'_, SharedHistoryStore>,
+    name: String,
+) -> Result<Collection, AppError> {
+    Ok(store.create_collection(&name).await?)
+}
+
+#[tauri::command]
+pub async fn rename_collection(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+    name: String,
+) -> Result<Collection, AppError> {
+    Ok(store.rename_collection(id, &name).await?)
+}
+
+#[tauri::command]
+pub async fn delete_collection(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+) -> Result<(), AppError> {
+    // The entries of the collection are kept
+    store.delete_collection(id).await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn add_to_collection(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+    collection_id: i64,
+) -> Result<(), AppError> {
+    store.add_to_collection(id, collection_id).await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn remove_from_collection(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+    collection_id: i64,
+) -> Result<(), AppError> {
+    store.remove_from_collection(id, collection_id).await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn add_tag(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+    tag: String,
+) -> Result<(), AppError> {
+    store.add_tag(id, &tag).await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn remove_tag(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+    tag: String,
+) -> Result<(), AppError> {
+    store.remove_tag(id, &tag).await?;
+
+    Ok(())
+}
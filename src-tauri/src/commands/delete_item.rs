@@ -1,25 +1,32 @@
-use tauri::Emitter;
+use tauri::{Emitter, State};
 
 use crate::core::{
     app_handle::app_handle,
-    database_api::{delete_all_items, delete_item_by_id},
+    errors::{AppError, DatabaseError},
+    history_store::SharedHistoryStore,
 };
 
 #[tauri::command]
-pub fn delete_item(id: i64) {
-    println!("Deleting item with ID: {}", id);
-    let res = delete_item_by_id(id);
-    let _ = app_handle().emit("delete-item", id);
+pub async fn delete_item(store: State<'_, SharedHistoryStore>, id: i64) -> Result<(), AppError> {
+    println!("Moving item with ID {} to the trash", id);
+    match store.trash(id).await {
+        // Already deleted (by the retention policy for instance), the window only needs to catch up
+        Ok(()) | Err(DatabaseError::NotFound(_)) => {}
+        Err(e) => return Err(e.into()),
+    }
+    app_handle().emit("delete-item", id)?;
 
-    return res;
+    Ok(())
 }
 
 #[tauri::command]
-pub fn delete_all() {
-    println!("Deleting all items");
-    let _ = delete_all_items();
+pub async fn delete_all(store: State<'_, SharedHistoryStore>) -> Result<(), AppError> {
+    println!("Moving all items to the trash");
+    store.trash_all().await?;
 
     let app = app_handle();
 
-    let _ = app.emit("delete-all-items", {}).unwrap();
+    app.emit("delete-all-items", {})?;
+
+    Ok(())
 }
@@ -0,0 +1,19 @@
+use std::sync::Arc;
+
+use tauri::State;
+
+use crate::{
+    core::{errors::AppError, profiles::ProfileStore},
+    structures::config::config,
+};
+
+// Function to change the key of the encrypted history (a new passphrase is required in passphrase mode)
+#[tauri::command]
+pub async fn rotate_encryption_key(
+    store: State<'_, Arc<ProfileStore>>,
+    passphrase: Option<String>,
+) -> Result<(), AppError> {
+    store.current().rotate_key(config(), passphrase).await?;
+
+    Ok(())
+}
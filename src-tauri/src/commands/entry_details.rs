@@ -0,0 +1,62 @@
+use tauri::State;
+
+use crate::core::{
+    errors::{AppError, DatabaseError},
+    history_store::SharedHistoryStore,
+    templates::Template,
+};
+
+#[tauri::command]
+pub async fn set_entry_title(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+    title: Option<String>,
+) -> Result<(), AppError> {
+    // A missing or blank title removes it, the content is shown again
+    store.set_title(id, title.as_deref()).await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_entry_note(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+    note: Option<String>,
+) -> Result<(), AppError> {
+    store.set_note(id, note.as_deref()).await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_entry_expiry(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+    minutes: Option<i64>,
+) -> Result<Option<String>, AppError> {
+    // Without a lifetime the entry is kept until deleted, the new expiry date is returned for the window
+    Ok(store.set_expiry(id, minutes).await?)
+}
+
+#[tauri::command]
+pub async fn set_entry_template(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+    is_template: bool,
+) -> Result<(), AppError> {
+    // Only plain text can hold placeholders, and a template must be valid to be used
+    if is_template {
+        let entry = store.get(id).await?;
+        if entry.entry_type == "image" || entry.entry_type == "rich_text" {
+            return Err(DatabaseError::InvalidInput(
+                "Only text entries can be templates".to_string(),
+            )
+            .into());
+        }
+        Template::parse(&entry.content)?;
+    }
+    store.set_template(id, is_template).await?;
+
+    Ok(())
+}
@@ -1,7 +1,15 @@
-use crate::core::database_api::set_forced_language;
+use tauri::State;
+
+use crate::core::{errors::AppError, history_store::SharedHistoryStore};
 
 #[tauri::command]
-pub fn force_language(id: i64, language: &str) {
-    // Call the function to set the forced language in the database
-    set_forced_language(id, language);
+pub async fn force_language(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+    language: String,
+) -> Result<(), AppError> {
+    // Set the forced language in the history
+    store.set_forced_language(id, &language).await?;
+
+    Ok(())
 }
@@ -1,10 +1,19 @@
+pub mod archive_api;
+pub mod backup_api;
+pub mod capture_api;
 pub mod clipboard_api;
+pub mod collections_api;
 pub mod delete_item;
+pub mod encryption_api;
+pub mod entry_details;
 pub mod force_language;
 pub mod manage_native_clipboard;
 pub mod pin_item;
+pub mod profiles_api;
 pub mod resize_window;
+pub mod search_entries;
 pub mod settings_api;
 pub mod show_window;
 pub mod toggle_window;
+pub mod trash_api;
 pub mod url_opener;
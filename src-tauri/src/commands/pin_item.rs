@@ -1,21 +1,23 @@
-use crate::core::database_api::{pin_item_by_id, unpin_all_items, unpin_item_by_id};
+use tauri::State;
+
+use crate::core::{errors::AppError, history_store::SharedHistoryStore};
 
 #[tauri::command]
-pub fn toggle_pin(id: i64, state: bool) -> bool {
-    // Set the state in the database
-    if state {
-        pin_item_by_id(id);
-    } else {
-        unpin_item_by_id(id);
-    }
+pub async fn toggle_pin(
+    store: State<'_, SharedHistoryStore>,
+    id: i64,
+    state: bool,
+) -> Result<bool, AppError> {
+    // Set the state in the history
+    store.set_pinned(id, state).await?;
 
-    return true;
+    Ok(true)
 }
 
 #[tauri::command]
-pub fn unpin_all() -> bool {
-    // Set the state in the database
-    let res = unpin_all_items();
+pub async fn unpin_all(store: State<'_, SharedHistoryStore>) -> Result<bool, AppError> {
+    // Set the state in the history
+    let res = store.unpin_all().await?;
 
-    return res > 0;
+    Ok(res > 0)
 }
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use tauri::{menu::MenuItem, Emitter, Manager, State, Wry};
+
+use crate::{
+    core::{
+        app_handle::app_handle,
+        errors::AppError,
+        paths::active_profile,
+        profiles::{self, ProfileStore},
+        tasks::clipboard_watcher::suspend_capture,
+    },
+    structures::profile::Profile,
+};
+
+// Tray menu item showing the active profile
+pub struct ProfileMenuItem(pub MenuItem<Wry>);
+
+// Function to get the label of the tray menu item showing the active profile
+pub fn profile_label(name: &str) -> String {
+    format!("👤 Profile: {}", name)
+}
+
+#[tauri::command]
+pub async fn list_profiles() -> Result<Vec<Profile>, AppError> {
+    Ok(profiles::list_profiles())
+}
+
+#[tauri::command]
+pub async fn create_profile(name: String) -> Result<Profile, AppError> {
+    Ok(profiles::create_profile(&name)?)
+}
+
+#[tauri::command]
+pub async fn delete_profile(name: String) -> Result<(), AppError> {
+    profiles::delete_profile(&name)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn switch_profile(
+    store: State<'_, Arc<ProfileStore>>,
+    name: String,
+) -> Result<(), AppError> {
+    // Nothing is captured while the history is replaced, so no entry lands in the wrong profile
+    let suspension = suspend_capture();
+    let store = store.inner().clone();
+    let switched =
+        tauri::async_runtime::spawn_blocking(move || profiles::switch_profile(&store, &name)).await;
+    drop(suspension);
+    switched??;
+
+    let app = app_handle();
+    let profile = active_profile();
+    if let Some(item) = app.try_state::<ProfileMenuItem>() {
+        item.0.set_text(profile_label(&profile))?;
+    }
+
+    // The window still shows the history and the settings of the previous profile
+    app.emit("profile-changed", &profile)?;
+    app.emit("reload-window", {})?;
+
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use tauri::State;
+
+use crate::{
+    core::{errors::AppError, history_store::SharedHistoryStore},
+    structures::{
+        config::{config, AppConfig},
+        entry_filters::EntryFilters,
+        search_result::SearchResult,
+    },
+};
+
+#[tauri::command]
+pub async fn search_entries(
+    store: State<'_, SharedHistoryStore>,
+    query: String,
+    filters: Option<EntryFilters>,
+) -> Result<Vec<SearchResult>, AppError> {
+    let configuration: AppConfig = config();
+
+    // Extract the filters written in the query (type:url, pinned:true, lang:rust, after:..., before:...)
+    let (text, query_filters) = EntryFilters::from_query(&query);
+
+    // Filters passed explicitly take precedence over the ones written in the query
+    let filters = query_filters.merge(filters.unwrap_or_default());
+
+    let preview_length = configuration.max_displayed_characters.max(0) as usize;
+    let results = store.search(&text, filters, preview_length).await?;
+
+    Ok(results)
+}
@@ -1,25 +1,35 @@
-use tauri::{Listener, Manager, Result, WebviewWindowBuilder};
+use tauri::{Listener, Manager, WebviewWindowBuilder};
 
 use crate::{
     commands::toggle_window::toggle_window,
-    core::app_handle::app_handle,
-    structures::config::{config, get_config_path, AppConfig},
+    core::{app_handle::app_handle, errors::AppError, paths::get_config_path},
+    structures::config::{config, AppConfig},
 };
 
-fn reload_main() {
+// Function to reload the webviews of a window, if it is open
+fn reload_window(label: &str) -> Result<(), AppError> {
+    if let Some(window) = app_handle().get_window(label) {
+        for webview in window.webviews() {
+            webview.reload()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn reload_main() -> Result<(), AppError> {
     // Get the app handle
     let app = app_handle();
 
-    // Get the main window
-    let w = app.get_window("main").unwrap();
-
     // Reload the main window
-    w.webviews()[0].reload().unwrap();
+    reload_window("main")?;
 
     // Listen for the end of the sliding animation to trigger it back so th window becomes visible again
     app.once("paste", move |_| {
         tauri::async_runtime::spawn(toggle_window(Some(true)));
     });
+
+    Ok(())
 }
 
 #[tauri::command]
@@ -81,68 +91,73 @@ pub fn get_config_value(property: Option<&str>) -> Option<String> {
 
 // Reset the config to default values
 #[tauri::command]
-pub fn reset_config() -> Result<()> {
+pub fn reset_config() -> Result<(), AppError> {
     // Get the default config as a JSON string
     let default = AppConfig::default();
-    let default_json = serde_json::to_string(&default).unwrap();
+    let default_json =
+        serde_json::to_string(&default).map_err(|e| AppError::Config(e.to_string()))?;
 
     // Get the current config
     let mut config = config();
 
     // Write it instead of the current config (in memory only)
-    config.update_from_json(&default_json).unwrap();
+    config
+        .update_from_json(&default_json)
+        .map_err(|e| AppError::Config(e.to_string()))?;
 
     // Reload the main window
-    reload_main();
+    reload_main()?;
 
     // Reload the settings window
-    if let Some(window) = app_handle().get_window("settings") {
-        window.webviews()[0].reload()?;
-    }
-
-    Ok(())
+    reload_window("settings")
 }
 
 #[tauri::command]
-pub fn preview_config(payload: String) {
+pub fn preview_config(payload: String) -> Result<(), AppError> {
     // Get the current config
     let mut config = config();
 
     // Update the config with the new values
-    let _ = config.update_from_json(&payload);
+    config
+        .update_from_json(&payload)
+        .map_err(|e| AppError::Config(e.to_string()))?;
 
     // Reload the main window with the new config
-    reload_main();
+    reload_main()
 }
 
 #[tauri::command]
-pub fn cancel_config(app: tauri::AppHandle) -> Result<()> {
+pub fn cancel_config(app: tauri::AppHandle) -> Result<(), AppError> {
     // Reload the config from the file
     config();
 
     // Reload the main window
-    app.get_window("main").unwrap().webviews()[0].reload()?;
+    reload_window("main")?;
 
     // Close the settings window
     if let Some(window) = app.get_window("settings") {
-        window.close().unwrap();
+        window.close()?;
     }
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn save_config(payload: String) {
+pub fn save_config(payload: String) -> Result<(), AppError> {
     // Get the current config
     let mut config = config();
 
     // Update the config with the new values
-    config.update_from_json(&payload).unwrap();
+    config
+        .update_from_json(&payload)
+        .map_err(|e| AppError::Config(e.to_string()))?;
 
     // Write the config to the file
     let path = get_config_path();
-    config.save_to_file(path.to_str().unwrap()).unwrap();
+    config
+        .save_to_file(&path.to_string_lossy())
+        .map_err(|e| AppError::Config(format!("Failed to write {}: {}", path.display(), e)))?;
 
     // Reload the main window with the new config
-    reload_main();
+    reload_main()
 }
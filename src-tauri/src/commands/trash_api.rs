@@ -0,0 +1,67 @@
+use tauri::{Emitter, State};
+
+use crate::{
+    commands::clipboard_api::DEFAULT_PAGE_SIZE,
+    core::{app_handle::app_handle, errors::AppError, history_store::SharedHistoryStore},
+    structures::{
+        config::config,
+        entry_filters::EntryFilters,
+        entry_page::{EntryPage, EntrySort, ListCursor},
+        operation::Operation,
+    },
+};
+
+#[tauri::command]
+pub async fn list_trash(
+    store: State<'_, SharedHistoryStore>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+) -> Result<EntryPage, AppError> {
+    // An invalid cursor restarts from the first page
+    let cursor: Option<ListCursor> = cursor.and_then(|c| serde_json::from_str(&c).ok());
+
+    // Most recently added entries first, like the history
+    let filters = EntryFilters {
+        trashed: true,
+        ..EntryFilters::default()
+    };
+
+    Ok(store
+        .list(
+            cursor,
+            limit.unwrap_or(DEFAULT_PAGE_SIZE),
+            EntrySort::Date,
+            filters,
+            config().max_displayed_characters.max(0) as usize,
+        )
+        .await?)
+}
+
+#[tauri::command]
+pub async fn restore_item(store: State<'_, SharedHistoryStore>, id: i64) -> Result<(), AppError> {
+    store.restore(id).await?;
+
+    // The restored entry goes back to its place in the history
+    app_handle().emit("reload-window", {})?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn empty_trash(store: State<'_, SharedHistoryStore>) -> Result<i64, AppError> {
+    Ok(store.empty_trash().await?)
+}
+
+#[tauri::command]
+pub async fn undo_last_operation(
+    store: State<'_, SharedHistoryStore>,
+) -> Result<Option<Operation>, AppError> {
+    let operation = store.undo_last_operation().await?;
+
+    // Entries may be back or pinned again, the window has to be reloaded
+    if operation.is_some() {
+        app_handle().emit("reload-window", {})?;
+    }
+
+    Ok(operation)
+}
@@ -0,0 +1,434 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{BufWriter, Read, Write},
+    path::Path,
+};
+
+use rusqlite::{params_from_iter, Connection, OptionalExtension};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    core::{
+        blob_store::{BlobStore, ImageBlob},
+        compression::decompress_text,
+        database_api::{
+            add_item_tag, collection_id_by_name, content_hash, filters_to_sql, frecency_event,
+            TAG_SEPARATOR,
+        },
+        errors::DatabaseError,
+    },
+    structures::{
+        archive::{
+            ArchiveEntry, ArchiveManifest, ImportConflict, ImportReport, ARCHIVE_FORMAT,
+            ARCHIVE_VERSION,
+        },
+        entry_filters::EntryFilters,
+        source_app::SourceApp,
+    },
+};
+
+// Layout of an archive (a zip file):
+//   manifest.json        ArchiveManifest, checked before anything is imported
+//   entries.ndjson       one ArchiveEntry per line, oldest entry first
+//   images/<hash>.png    PNG file of every image entry, never encrypted
+const MANIFEST_FILE: &str = "manifest.json";
+const ENTRIES_FILE: &str = "entries.ndjson";
+const IMAGES_DIR: &str = "images";
+
+// Function to convert an error of the zip library
+fn archive_error(e: zip::result::ZipError) -> DatabaseError {
+    DatabaseError::Archive(e.to_string())
+}
+
+// Function to export the entries matching `filters` to an archive, returns the number of exported entries
+pub fn export_archive(
+    conn: &Connection,
+    blobs: &BlobStore,
+    path: &Path,
+    filters: &EntryFilters,
+) -> Result<usize, DatabaseError> {
+    let (conditions, params) = filters_to_sql(filters);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT e.type, e.content, e.content_hash, e.blob_hash, e.added_at, e.pinned,
+            e.forced_language, e.paste_count, e.last_used_at,
+            (SELECT group_concat(t.tag, char(31)) FROM entry_tags t WHERE t.entry_id = e.id),
+            (SELECT group_concat(c.name, char(31)) FROM entry_collections l
+                JOIN collections c ON c.id = l.collection_id WHERE l.entry_id = e.id),
+            e.source_class, e.source_title, e.source_process, e.title, e.note,
+            e.is_template, e.compressed_content, e.sensitive, e.masked_content, e.expires_at
+        FROM clipboard_entries e
+        WHERE {}
+        ORDER BY e.added_at ASC, e.id ASC",
+        conditions.join(" AND ")
+    ))?;
+
+    let split = |value: Option<String>| -> Vec<String> {
+        value
+            .map(|v| v.split(TAG_SEPARATOR).map(str::to_string).collect())
+            .unwrap_or_default()
+    };
+    let rows = stmt.query_map(params_from_iter(params), |row| {
+        let blob_hash: Option<String> = row.get(3)?;
+        let source = SourceApp {
+            class: row.get(11)?,
+            title: row.get(12)?,
+            process: row.get(13)?,
+        };
+        let entry = ArchiveEntry {
+            entry_type: row.get(0)?,
+            content: row.get(1)?,
+            image: blob_hash
+                .as_ref()
+                .map(|hash| format!("{}/{}.png", IMAGES_DIR, hash)),
+            content_hash: row.get(2)?,
+            added_at: row.get(4)?,
+            pinned: row.get::<_, i32>(5)? != 0,
+            forced_language: row.get(6)?,
+            paste_count: row.get(7)?,
+            last_used_at: row.get(8)?,
+            tags: split(row.get(9)?),
+            collections: split(row.get(10)?),
+            source: (!source.is_empty()).then_some(source),
+            title: row.get(14)?,
+            note: row.get(15)?,
+            is_template: row.get::<_, i32>(16)? != 0,
+            sensitive: row.get(18)?,
+            masked_content: row.get(19)?,
+            expires_at: row.get(20)?,
+        };
+        Ok((entry, blob_hash, row.get::<_, Option<Vec<u8>>>(17)?))
+    })?;
+
+    // Write to a temporary file first so a failed export never leaves a truncated archive behind
+    let temp_path = path.with_extension("tmp");
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(&temp_path)?));
+    let compressed = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    let mut lines = Vec::new();
+    let mut images: HashSet<String> = HashSet::new();
+    for row in rows {
+        let (mut entry, blob_hash, compressed_content) = row?;
+
+        // Texts too large to be stored in plain are exported whole
+        if let Some(data) = compressed_content {
+            entry.content = decompress_text(&data).map_err(DatabaseError::Compression)?;
+        }
+
+        // Images are already compressed, they are stored as is
+        if let Some(hash) = blob_hash {
+            if !images.contains(&hash) {
+                let bytes = blobs.load_image(&hash).ok_or_else(|| {
+                    DatabaseError::Blob(format!("The image {} can't be read", hash))
+                })?;
+                zip.start_file(format!("{}/{}.png", IMAGES_DIR, hash), stored)
+                    .map_err(archive_error)?;
+                zip.write_all(&bytes)?;
+                images.insert(hash);
+            }
+        }
+
+        lines.push(
+            serde_json::to_string(&entry).map_err(|e| DatabaseError::Archive(e.to_string()))?,
+        );
+    }
+
+    zip.start_file(ENTRIES_FILE, compressed)
+        .map_err(archive_error)?;
+    for line in &lines {
+        zip.write_all(line.as_bytes())?;
+        zip.write_all(b"\n")?;
+    }
+
+    let manifest = ArchiveManifest {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        exported_at: conn.query_row("SELECT datetime('now')", [], |row| row.get(0))?,
+        entry_count: lines.len(),
+    };
+    zip.start_file(MANIFEST_FILE, compressed)
+        .map_err(archive_error)?;
+    zip.write_all(
+        &serde_json::to_vec_pretty(&manifest).map_err(|e| DatabaseError::Archive(e.to_string()))?,
+    )?;
+
+    zip.finish().map_err(archive_error)?.flush()?;
+    fs::rename(&temp_path, path)?;
+
+    println!("Exported {} clipboard entries to {:?}", lines.len(), path);
+    Ok(lines.len())
+}
+
+// Function to read a file of an archive
+fn read_archive_file(archive: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, DatabaseError> {
+    let mut file = archive.by_name(name).map_err(archive_error)?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+// Function to recompute the frecency of an imported entry from its dates (the uses are counted at the capture)
+fn refresh_frecency(conn: &Connection, id: i64, keep_higher: bool) -> Result<(), DatabaseError> {
+    let (days, paste_count): (Option<f64>, i64) = conn.query_row(
+        "SELECT julianday(added_at) - julianday('1970-01-01'), paste_count
+        FROM clipboard_entries WHERE id = ?1",
+        [id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let frecency =
+        frecency_event(days.unwrap_or_default()) + ((1 + paste_count.max(0)) as f64).log2();
+
+    conn.execute(
+        match keep_higher {
+            true => "UPDATE clipboard_entries SET frecency = MAX(frecency, ?1) WHERE id = ?2",
+            false => "UPDATE clipboard_entries SET frecency = ?1 WHERE id = ?2",
+        },
+        (frecency, id),
+    )?;
+    Ok(())
+}
+
+// Function to add the tags and collections of an imported entry (invalid tags are dropped)
+fn add_links(conn: &Connection, id: i64, entry: &ArchiveEntry) -> Result<(), DatabaseError> {
+    for tag in &entry.tags {
+        match add_item_tag(conn, id, tag) {
+            Ok(()) | Err(DatabaseError::InvalidInput(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    for name in &entry.collections {
+        let collection_id = match collection_id_by_name(conn, name) {
+            Ok(collection_id) => collection_id,
+            Err(DatabaseError::InvalidInput(_)) => continue,
+            Err(e) => return Err(e),
+        };
+        conn.execute(
+            "INSERT OR IGNORE INTO entry_collections (entry_id, collection_id) VALUES (?1, ?2)",
+            (id, collection_id),
+        )?;
+    }
+    Ok(())
+}
+
+// Function to insert an imported entry, keeping its dates and marks (invalid dates are replaced by the current date)
+fn insert_imported(
+    conn: &Connection,
+    entry: &ArchiveEntry,
+    hash: &str,
+    image: Option<&ImageBlob>,
+) -> Result<(), DatabaseError> {
+    let source = entry.source.clone().unwrap_or_default();
+    conn.execute(
+        "INSERT INTO clipboard_entries
+            (type, content, pinned, content_hash, byte_size, blob_hash, image_width, image_height,
+            mime_type, forced_language, paste_count, last_used_at, added_at,
+            source_class, source_title, source_process, title, note, is_template,
+            sensitive, masked_content, expires_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, datetime(?12),
+            COALESCE(datetime(?13), CURRENT_TIMESTAMP), ?14, ?15, ?16, ?17, ?18, ?19,
+            ?20, ?21, datetime(?22))",
+        rusqlite::params![
+            entry.entry_type,
+            entry.content,
+            entry.pinned as i32,
+            hash,
+            image
+                .map(|i| i.byte_size)
+                .unwrap_or(entry.content.len() as i64),
+            image.map(|i| i.hash.as_str()),
+            image.map(|i| i.width),
+            image.map(|i| i.height),
+            image.map(|i| i.mime_type),
+            entry.forced_language,
+            entry.paste_count.max(0),
+            entry.last_used_at,
+            entry.added_at,
+            source.class,
+            source.title,
+            source.process,
+            entry.title,
+            entry.note,
+            entry.is_template as i32,
+            entry.sensitive,
+            entry.masked_content,
+            entry.expires_at,
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    add_links(conn, id, entry)?;
+    refresh_frecency(conn, id, false)
+}
+
+// Function to replace the metadata of an existing entry with the imported one
+fn overwrite_entry(conn: &Connection, id: i64, entry: &ArchiveEntry) -> Result<(), DatabaseError> {
+    conn.execute(
+        "UPDATE clipboard_entries SET
+            added_at = COALESCE(datetime(?1), added_at),
+            pinned = ?2,
+            forced_language = ?3,
+            paste_count = ?4,
+            last_used_at = datetime(?5),
+            title = ?6,
+            note = ?7,
+            is_template = ?8,
+            sensitive = ?9,
+            masked_content = ?10,
+            expires_at = datetime(?11)
+        WHERE id = ?12",
+        rusqlite::params![
+            entry.added_at,
+            entry.pinned as i32,
+            entry.forced_language,
+            entry.paste_count.max(0),
+            entry.last_used_at,
+            entry.title,
+            entry.note,
+            entry.is_template as i32,
+            entry.sensitive,
+            entry.masked_content,
+            entry.expires_at,
+            id,
+        ],
+    )?;
+
+    conn.execute("DELETE FROM entry_tags WHERE entry_id = ?1", [id])?;
+    conn.execute("DELETE FROM entry_collections WHERE entry_id = ?1", [id])?;
+    add_links(conn, id, entry)?;
+    refresh_frecency(conn, id, false)
+}
+
+// Function to merge an imported entry into an existing one
+// The most recent dates, the highest usage count and the shortest lifetime win, the tags and collections are combined
+// An entry holding a secret on either side stays marked as such
+fn merge_entry(conn: &Connection, id: i64, entry: &ArchiveEntry) -> Result<(), DatabaseError> {
+    conn.execute(
+        "UPDATE clipboard_entries SET
+            added_at = COALESCE(MAX(added_at, datetime(?1)), added_at),
+            pinned = MAX(pinned, ?2),
+            forced_language = COALESCE(forced_language, ?3),
+            paste_count = MAX(paste_count, ?4),
+            last_used_at = COALESCE(MAX(last_used_at, datetime(?5)), last_used_at, datetime(?5)),
+            title = COALESCE(title, ?6),
+            note = COALESCE(note, ?7),
+            is_template = MAX(is_template, ?8),
+            sensitive = COALESCE(sensitive, ?9),
+            masked_content = COALESCE(masked_content, ?10),
+            expires_at = COALESCE(MIN(expires_at, datetime(?11)), expires_at, datetime(?11))
+        WHERE id = ?12",
+        rusqlite::params![
+            entry.added_at,
+            entry.pinned as i32,
+            entry.forced_language,
+            entry.paste_count.max(0),
+            entry.last_used_at,
+            entry.title,
+            entry.note,
+            entry.is_template as i32,
+            entry.sensitive,
+            entry.masked_content,
+            entry.expires_at,
+            id,
+        ],
+    )?;
+
+    add_links(conn, id, entry)?;
+    refresh_frecency(conn, id, true)
+}
+
+// Function to import an archive into the history, duplicates (same type and content) are handled according to `conflict`
+// The import is atomic: on error nothing is added to the history
+pub fn import_archive(
+    conn: &mut Connection,
+    blobs: &BlobStore,
+    path: &Path,
+    conflict: ImportConflict,
+) -> Result<ImportReport, DatabaseError> {
+    let mut archive = ZipArchive::new(File::open(path)?).map_err(archive_error)?;
+
+    // Refuse archives written by another application or a newer version
+    let manifest: ArchiveManifest =
+        serde_json::from_slice(&read_archive_file(&mut archive, MANIFEST_FILE)?)
+            .map_err(|e| DatabaseError::Archive(format!("Invalid manifest: {}", e)))?;
+    if manifest.format != ARCHIVE_FORMAT || manifest.version > ARCHIVE_VERSION {
+        return Err(DatabaseError::Archive(format!(
+            "Unsupported archive ({} version {})",
+            manifest.format, manifest.version
+        )));
+    }
+
+    let entries = String::from_utf8(read_archive_file(&mut archive, ENTRIES_FILE)?)
+        .map_err(|e| DatabaseError::Archive(e.to_string()))?;
+
+    let tx = conn.transaction()?;
+    let mut report = ImportReport::default();
+
+    for line in entries.lines().filter(|line| !line.trim().is_empty()) {
+        let Ok(entry) = serde_json::from_str::<ArchiveEntry>(line) else {
+            report.failed += 1;
+            continue;
+        };
+
+        // The hash is computed again rather than trusted, it identifies duplicates
+        let image = match (entry.entry_type.as_str(), &entry.image) {
+            ("image", Some(name)) => {
+                let blob = read_archive_file(&mut archive, name)
+                    .map_err(|e| e.to_string())
+                    .and_then(|bytes| blobs.store_image_bytes(bytes));
+                match blob {
+                    Ok(blob) => Some(blob),
+                    Err(e) => {
+                        eprintln!("Failed to import the image {}: {}", name, e);
+                        report.failed += 1;
+                        continue;
+                    }
+                }
+            }
+            ("image", None) => {
+                report.failed += 1;
+                continue;
+            }
+            _ => None,
+        };
+        let hash = match &image {
+            Some(blob) => blob.hash.clone(),
+            None => content_hash(&entry.content),
+        };
+
+        let existing: Option<i64> = tx
+            .query_row(
+                "SELECT id FROM clipboard_entries
+                WHERE content_hash = ?1 AND type = ?2 AND deleted_at IS NULL
+                ORDER BY added_at DESC LIMIT 1",
+                (&hash, &entry.entry_type),
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match (existing, conflict) {
+            (None, _) => {
+                insert_imported(&tx, &entry, &hash, image.as_ref())?;
+                report.imported += 1;
+            }
+            (Some(_), ImportConflict::Skip) => report.skipped += 1,
+            (Some(id), ImportConflict::Overwrite) => {
+                overwrite_entry(&tx, id, &entry)?;
+                report.overwritten += 1;
+            }
+            (Some(id), ImportConflict::Merge) => {
+                merge_entry(&tx, id, &entry)?;
+                report.merged += 1;
+            }
+        }
+    }
+
+    tx.commit()?;
+
+    // Images of skipped entries are not referenced
+    blobs.collect_orphans(conn);
+
+    println!("Imported clipboard history from {:?}: {:?}", path, report);
+    Ok(report)
+}
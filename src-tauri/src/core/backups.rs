@@ -0,0 +1,252 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use rusqlite::{backup::Backup as OnlineBackup, Connection};
+
+use crate::{
+    core::{
+        blob_store::BlobStore,
+        encryption::{
+            export_database, get_key_path, get_salt_path, open_database_copy, open_with_key,
+        },
+        errors::DatabaseError,
+    },
+    structures::{backup::Backup, config::AppConfig},
+};
+
+// Name of the database file in a backup directory, the key files keep their usual name next to it
+const BACKUP_DATABASE: &str = "database.db";
+
+// Number of pages copied per step of the online backup
+const PAGES_PER_STEP: i32 = 256;
+
+// Function to get the directory of the backups of a database (next to it)
+pub fn get_backups_dir(db_path: &Path) -> PathBuf {
+    db_path.with_file_name("backups")
+}
+
+// Function to convert a backup ID (YYYYMMDD-HHMMSS-mmm) to a date (YYYY-MM-DD HH:MM:SS), None if it isn't an ID
+// The IDs of the backups made before the milliseconds were added (YYYYMMDD-HHMMSS) are still recognized
+fn backup_date(id: &str) -> Option<String> {
+    let valid = (id.len() == 15 || id.len() == 19)
+        && id.char_indices().all(|(i, c)| match i {
+            8 | 15 => c == '-',
+            _ => c.is_ascii_digit(),
+        });
+    valid.then(|| {
+        format!(
+            "{}-{}-{} {}:{}:{}",
+            &id[0..4],
+            &id[4..6],
+            &id[6..8],
+            &id[9..11],
+            &id[11..13],
+            &id[13..15]
+        )
+    })
+}
+
+// Function to get the backups of a database, the most recent first
+pub fn list_backups(db_path: &Path) -> Vec<Backup> {
+    let Ok(dir) = fs::read_dir(get_backups_dir(db_path)) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<Backup> = dir
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let id = entry.file_name().to_string_lossy().to_string();
+            let created_at = backup_date(&id)?;
+            let size = fs::metadata(entry.path().join(BACKUP_DATABASE)).ok()?.len();
+            Some(Backup {
+                id,
+                created_at,
+                size,
+            })
+        })
+        .collect();
+
+    backups.sort_by(|a, b| b.id.cmp(&a.id));
+    backups
+}
+
+// Function to get the time elapsed since the most recent backup (None if there is no backup)
+pub fn time_since_last_backup(db_path: &Path) -> Option<Duration> {
+    let latest = list_backups(db_path).into_iter().next()?;
+    let path = get_backups_dir(db_path)
+        .join(&latest.id)
+        .join(BACKUP_DATABASE);
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(
+        SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default(),
+    )
+}
+
+// Function to link a file into a backup, copied when the file system doesn't support hard links
+fn link_or_copy(source: &Path, dest: &Path) -> std::io::Result<()> {
+    if dest.exists() {
+        return Ok(());
+    }
+    fs::hard_link(source, dest).or_else(|_| fs::copy(source, dest).map(|_| ()))
+}
+
+// Function to take a snapshot of the database with the online backup API, the history stays usable meanwhile
+// The backup is encrypted with the same key, whose files are kept along with the images it references
+// (blobs never change once written, they are hard linked when possible)
+pub fn create_backup(
+    conn: &Connection,
+    blobs: &BlobStore,
+    db_path: &Path,
+    key: Option<&[u8; 32]>,
+) -> Result<Backup, DatabaseError> {
+    // The milliseconds keep apart the backups made within the same second (the safety backup of a restore)
+    let id: String = conn.query_row(
+        "SELECT strftime('%Y%m%d-%H%M%S-', 'now') || substr(strftime('%f', 'now'), 4)",
+        [],
+        |row| row.get(0),
+    )?;
+    let dir = get_backups_dir(db_path).join(&id);
+    if dir.exists() {
+        return Err(DatabaseError::InvalidInput(format!(
+            "The backup {} already exists",
+            id
+        )));
+    }
+
+    // Build the backup in a temporary directory, it only appears in the list once complete
+    let partial_dir = get_backups_dir(db_path).join(format!("{}.partial", id));
+    let _ = fs::remove_dir_all(&partial_dir);
+    fs::create_dir_all(partial_dir.join("blobs"))?;
+
+    {
+        let mut dest = open_with_key(&partial_dir.join(BACKUP_DATABASE), key)
+            .map_err(DatabaseError::Encryption)?;
+        OnlineBackup::new(conn, &mut dest)?.run_to_completion(
+            PAGES_PER_STEP,
+            Duration::ZERO,
+            None,
+        )?;
+    }
+
+    for key_file in [get_key_path(db_path), get_salt_path(db_path)] {
+        if let Some(name) = key_file.file_name().filter(|_| key_file.exists()) {
+            fs::copy(&key_file, partial_dir.join(name))?;
+        }
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT blob_hash FROM clipboard_entries WHERE blob_hash IS NOT NULL")?;
+    let hashes = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    let backup_blobs = BlobStore::new(partial_dir.join("blobs"));
+    for hash in hashes {
+        let source = blobs.blob_path(&hash);
+        if source.exists() {
+            link_or_copy(&source, &backup_blobs.blob_path(&hash))?;
+        }
+    }
+
+    fs::rename(&partial_dir, &dir)?;
+    println!("Backed up the database to {:?}", dir);
+
+    Ok(Backup {
+        created_at: backup_date(&id).unwrap_or_default(),
+        size: fs::metadata(dir.join(BACKUP_DATABASE))?.len(),
+        id,
+    })
+}
+
+// Function to delete the backups beyond the rotation policy, returns the number of deleted backups
+// The latest backup of each of the `keep_daily` most recent days and `keep_weekly` most recent weeks is kept, and the
+// most recent backup is always kept whatever the policy
+pub fn rotate_backups(
+    conn: &Connection,
+    db_path: &Path,
+    keep_daily: usize,
+    keep_weekly: usize,
+) -> Result<usize, DatabaseError> {
+    let mut days: HashSet<i64> = HashSet::new();
+    let mut weeks: HashSet<i64> = HashSet::new();
+    let mut deleted = 0;
+
+    for (index, backup) in list_backups(db_path).into_iter().enumerate() {
+        // Days since 1970-01-01 (a Thursday, weeks start on Monday)
+        let day: i64 = conn.query_row(
+            "SELECT CAST(julianday(?1) - julianday('1970-01-01') AS INTEGER)",
+            [&backup.created_at],
+            |row| row.get(0),
+        )?;
+        let week = (day + 3).div_euclid(7);
+
+        let mut keep = index == 0;
+        if !days.contains(&day) && days.len() < keep_daily {
+            days.insert(day);
+            keep = true;
+        }
+        if !weeks.contains(&week) && weeks.len() < keep_weekly {
+            weeks.insert(week);
+            keep = true;
+        }
+
+        if !keep {
+            fs::remove_dir_all(get_backups_dir(db_path).join(&backup.id))?;
+            deleted += 1;
+        }
+    }
+
+    Ok(deleted)
+}
+
+// Function to replace the content of the database with a backup, through the writer connection
+// The current history is backed up first and the images of the backup are brought back, the caller brings
+// the schema up to date afterwards
+pub fn restore_backup(
+    conn: &mut Connection,
+    blobs: &BlobStore,
+    db_path: &Path,
+    configuration: &AppConfig,
+    key: Option<&[u8; 32]>,
+    id: &str,
+) -> Result<(), DatabaseError> {
+    let dir = get_backups_dir(db_path).join(id);
+    if backup_date(id).is_none() || !dir.join(BACKUP_DATABASE).exists() {
+        return Err(DatabaseError::BackupNotFound(id.to_string()));
+    }
+
+    create_backup(conn, blobs, db_path, key)?;
+
+    if let Ok(files) = fs::read_dir(dir.join("blobs")) {
+        fs::create_dir_all(blobs.dir())?;
+        for file in files.filter_map(Result::ok) {
+            link_or_copy(&file.path(), &blobs.dir().join(file.file_name()))?;
+        }
+    }
+
+    // The key may have been rotated since the backup, copy it with the current key first
+    let (source, source_key) = open_database_copy(&dir.join(BACKUP_DATABASE), configuration, key)
+        .map_err(DatabaseError::Encryption)?;
+    let converted_path = db_path.with_file_name("restoring.db");
+    let source = if source_key.as_ref() == key {
+        source
+    } else {
+        let _ = fs::remove_file(&converted_path);
+        export_database(&source, &converted_path, key)?;
+        open_with_key(&converted_path, key).map_err(DatabaseError::Encryption)?
+    };
+
+    let restored = OnlineBackup::new(&source, conn)
+        .and_then(|backup| backup.run_to_completion(PAGES_PER_STEP, Duration::ZERO, None));
+    drop(source);
+    let _ = fs::remove_file(&converted_path);
+    restored?;
+
+    println!("Restored the database from the backup {}", id);
+    Ok(())
+}
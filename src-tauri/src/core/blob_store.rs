@@ -0,0 +1,213 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+
+use crate::core::encryption::{decrypt_blob, encrypt_blob};
+
+// Signature at the beginning of every PNG file
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// Struct to represent an image stored in the blob store
+#[derive(Clone, Debug)]
+pub struct ImageBlob {
+    pub hash: String, // SHA-256 of the PNG file, also its name in the store
+    pub width: u32,
+    pub height: u32,
+    pub byte_size: i64,
+    pub mime_type: &'static str,
+}
+
+// Key of the blobs and whether new blobs are encrypted (the key is kept to read old blobs when disabled)
+#[derive(Clone, Copy, Default)]
+struct BlobCipher {
+    key: Option<[u8; 32]>,
+    encrypt: bool,
+}
+
+// Struct to represent the directory where the images are stored, named after their hash
+// Clones share the same key
+#[derive(Clone)]
+pub struct BlobStore {
+    dir: PathBuf,
+    cipher: Arc<RwLock<BlobCipher>>,
+}
+
+// Function to read the dimensions of a PNG image from its header
+fn png_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if bytes.len() < 24 || !bytes.starts_with(PNG_SIGNATURE) || &bytes[12..16] != b"IHDR" {
+        return None;
+    }
+
+    let width = u32::from_be_bytes(bytes[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(bytes[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+impl BlobStore {
+    pub fn new(dir: PathBuf) -> BlobStore {
+        BlobStore {
+            dir,
+            cipher: Arc::new(RwLock::new(BlobCipher::default())),
+        }
+    }
+
+    // Function to get the blob store of a database file (in a directory next to it)
+    pub fn next_to(db_path: &Path) -> BlobStore {
+        BlobStore::new(db_path.with_file_name("blobs"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Function to set the key of the blobs, loaded from the database by `init_blob_encryption`
+    // Blobs written before the key is set stay in plaintext until they are converted at the next start
+    pub fn set_key(&self, key: Option<[u8; 32]>, encrypt: bool) {
+        *self.cipher.write().unwrap_or_else(PoisonError::into_inner) = BlobCipher { key, encrypt };
+    }
+
+    // Function to encrypt the content of a blob when encryption is enabled
+    fn seal(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let cipher = *self.cipher.read().unwrap_or_else(PoisonError::into_inner);
+        match cipher.key.filter(|_| cipher.encrypt) {
+            Some(key) => encrypt_blob(data, &key),
+            None => Ok(data.to_vec()),
+        }
+    }
+
+    // Function to decrypt the content of a blob (plaintext blobs are returned unchanged)
+    fn unseal(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let cipher = *self.cipher.read().unwrap_or_else(PoisonError::into_inner);
+        decrypt_blob(data, cipher.key.as_ref())
+    }
+
+    // Function to get the path of a blob from its hash
+    pub fn blob_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{}.png", hash))
+    }
+
+    // Function to store a base64 encoded image in the blob store, converted to PNG if needed
+    pub fn store_image(&self, base64_image: &str) -> Result<ImageBlob, String> {
+        let bytes = STANDARD
+            .decode(base64_image.trim())
+            .map_err(|e| format!("Invalid base64 image: {}", e))?;
+        self.store_image_bytes(bytes)
+    }
+
+    // Function to store an image file in the blob store, converted to PNG if needed
+    pub fn store_image_bytes(&self, mut bytes: Vec<u8>) -> Result<ImageBlob, String> {
+        // The clipboard gives PNG images on every platform, convert anything else
+        if !bytes.starts_with(PNG_SIGNATURE) {
+            let image =
+                image::load_from_memory(&bytes).map_err(|e| format!("Unsupported image: {}", e))?;
+            let mut png = Cursor::new(Vec::new());
+            image
+                .write_to(&mut png, image::ImageFormat::Png)
+                .map_err(|e| format!("Failed to convert image to PNG: {}", e))?;
+            bytes = png.into_inner();
+        }
+
+        let (width, height) = png_dimensions(&bytes).ok_or("Invalid PNG header")?;
+        let hash = format!("{:x}", Sha256::digest(&bytes));
+
+        // Content addressed: an existing blob already holds the same image
+        let path = self.blob_path(&hash);
+        if !path.exists() {
+            fs::create_dir_all(&self.dir)
+                .map_err(|e| format!("Failed to create the blob store: {}", e))?;
+
+            // Write to a temporary file first so a crash never leaves a truncated blob behind
+            let temp_path = path.with_extension("png.tmp");
+            fs::write(&temp_path, self.seal(&bytes)?)
+                .map_err(|e| format!("Failed to write blob: {}", e))?;
+            fs::rename(&temp_path, &path).map_err(|e| format!("Failed to write blob: {}", e))?;
+        }
+
+        Ok(ImageBlob {
+            hash,
+            width,
+            height,
+            byte_size: bytes.len() as i64,
+            mime_type: "image/png",
+        })
+    }
+
+    // Function to load a PNG image from the blob store, decrypted
+    pub fn load_image(&self, hash: &str) -> Option<Vec<u8>> {
+        match fs::read(self.blob_path(hash))
+            .map_err(|e| e.to_string())
+            .and_then(|data| self.unseal(&data))
+        {
+            Ok(bytes) => Some(bytes),
+            Err(e) => {
+                eprintln!("Failed to read blob {}: {}", hash, e);
+                None
+            }
+        }
+    }
+
+    // Function to load an image from the blob store as base64
+    pub fn load_image_base64(&self, hash: &str) -> Option<String> {
+        self.load_image(hash).map(|bytes| STANDARD.encode(bytes))
+    }
+
+    // Function to delete a blob if no entry references it anymore
+    pub fn remove_if_orphan(&self, conn: &Connection, hash: &str) {
+        let references: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM clipboard_entries WHERE blob_hash = ?1",
+                [hash],
+                |row| row.get(0),
+            )
+            .unwrap_or(1);
+
+        if references == 0 {
+            let _ = fs::remove_file(self.blob_path(hash));
+        }
+    }
+
+    // Function to delete every blob that is not referenced by an entry, returns the number of deleted blobs
+    pub fn collect_orphans(&self, conn: &Connection) -> usize {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return 0;
+        };
+
+        let referenced: HashSet<String> = match conn
+            .prepare("SELECT DISTINCT blob_hash FROM clipboard_entries WHERE blob_hash IS NOT NULL")
+        {
+            Ok(mut stmt) => stmt
+                .query_map([], |row| row.get::<_, String>(0))
+                .map(|rows| rows.filter_map(Result::ok).collect())
+                .unwrap_or_default(),
+            Err(e) => {
+                eprintln!("Failed to list referenced blobs: {}", e);
+                return 0;
+            }
+        };
+
+        let mut deleted = 0;
+        for file in dir.filter_map(Result::ok) {
+            let name = file.file_name().to_string_lossy().to_string();
+
+            // Leftovers of interrupted writes are removed as well
+            let orphan = match name.strip_suffix(".png") {
+                Some(hash) => !referenced.contains(hash),
+                None => name.ends_with(".png.tmp"),
+            };
+
+            if orphan && fs::remove_file(file.path()).is_ok() {
+                deleted += 1;
+            }
+        }
+
+        deleted
+    }
+}
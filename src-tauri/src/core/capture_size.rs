@@ -0,0 +1,36 @@
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+
+use crate::core::compression::text_prefix;
+
+// Function to format a size in bytes for the user (KB under a MB, MB above)
+pub fn format_size(bytes: usize) -> String {
+    match bytes {
+        b if b < 1024 * 1024 => format!("{} KB", b.div_ceil(1024)),
+        b => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+// Function to cut a text to `max_bytes`, a marker at the end telling the size of the whole text
+pub fn truncate_text(text: &str, max_bytes: usize) -> String {
+    let marker = format!(
+        "\n\n[Truncated by TactiClip, {} copied]",
+        format_size(text.len())
+    );
+    let mut truncated = text_prefix(text, max_bytes.saturating_sub(marker.len())).to_string();
+    truncated.push_str(&marker);
+    truncated
+}
+
+// Function to tell the user how a large content was handled, with a desktop notification
+pub fn notify_capture(app: &AppHandle, message: &str) {
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title("TactiClip")
+        .body(message)
+        .show()
+    {
+        eprintln!("Failed to show the notification \"{}\": {}", message, e);
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Local, Utc};
+use once_cell::sync::Lazy;
+use tauri::{menu::MenuItem, AppHandle, Emitter, Manager, Wry};
+
+use crate::structures::capture_state::{CaptureState, PauseReason};
+
+// Tray menu item pausing or resuming the capture
+pub struct PauseMenuItem(pub MenuItem<Wry>);
+
+// Struct to represent what keeps the capture paused, any of them is enough
+#[derive(Default)]
+struct PauseTriggers {
+    manual: bool,                   // Paused from a command or the tray
+    until: Option<DateTime<Local>>, // End of a timed pause, None to stay paused until resumed
+    screen_locked: bool,            // Paused while the screen is locked (pause_on_screen_lock)
+}
+
+// Global pause of the capture
+static PAUSE: Lazy<Mutex<PauseTriggers>> = Lazy::new(|| Mutex::new(PauseTriggers::default()));
+
+// Implement methods for PauseTriggers
+impl PauseTriggers {
+    // Function to check if the manual pause is still running
+    fn manual_active(&self) -> bool {
+        self.manual && self.until.is_none_or(|until| Local::now() < until)
+    }
+
+    // Function to get the state of the capture
+    fn state(&self) -> CaptureState {
+        let manual = self.manual_active();
+        let reason = if manual && self.until.is_some() {
+            Some(PauseReason::Timer)
+        } else if manual {
+            Some(PauseReason::Manual)
+        } else if self.screen_locked {
+            Some(PauseReason::ScreenLocked)
+        } else {
+            None
+        };
+
+        CaptureState {
+            paused: reason.is_some(),
+            reason,
+            paused_until: self.until.filter(|_| manual).map(|until| {
+                until
+                    .with_timezone(&Utc)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            }),
+        }
+    }
+}
+
+// Function to get the state of the capture
+pub fn capture_state() -> CaptureState {
+    PAUSE.lock().unwrap().state()
+}
+
+// Function to check if the clipboard must not be captured
+pub fn is_capture_paused() -> bool {
+    let triggers = PAUSE.lock().unwrap();
+    triggers.manual_active() || triggers.screen_locked
+}
+
+// Function to pause the capture, for a number of minutes or until resumed
+pub fn pause_capture(minutes: Option<u64>) {
+    let mut triggers = PAUSE.lock().unwrap();
+    triggers.manual = true;
+    triggers.until = minutes.map(|minutes| Local::now() + Duration::minutes(minutes as i64));
+}
+
+// Function to resume the capture (a pause due to the locked screen lasts until the screen is unlocked)
+pub fn resume_capture() {
+    let mut triggers = PAUSE.lock().unwrap();
+    triggers.manual = false;
+    triggers.until = None;
+}
+
+// Function to end a timed pause once its duration has elapsed, returns true if it ended
+pub fn end_elapsed_pause() -> bool {
+    let mut triggers = PAUSE.lock().unwrap();
+    if triggers.manual && !triggers.manual_active() {
+        triggers.manual = false;
+        triggers.until = None;
+        return true;
+    }
+    false
+}
+
+// Function to record whether the screen is locked, returns true if it changed
+pub fn set_screen_locked(locked: bool) -> bool {
+    let mut triggers = PAUSE.lock().unwrap();
+    let changed = triggers.screen_locked != locked;
+    triggers.screen_locked = locked;
+    changed
+}
+
+// Function to get the tooltip of the tray icon for a state of the capture
+fn tray_tooltip(state: &CaptureState) -> String {
+    match state.reason {
+        None => "TactiClip".to_string(),
+        Some(PauseReason::Manual) => "TactiClip (capture paused)".to_string(),
+        Some(PauseReason::ScreenLocked) => "TactiClip (capture paused, screen locked)".to_string(),
+        Some(PauseReason::Timer) => {
+            let until = PAUSE.lock().unwrap().until.unwrap_or_else(Local::now);
+            format!("TactiClip (capture paused until {})", until.format("%H:%M"))
+        }
+    }
+}
+
+// Function to get the label of the tray menu item pausing or resuming the capture
+pub fn pause_menu_label(paused: bool) -> &'static str {
+    if paused {
+        "▶️ Resume capture"
+    } else {
+        "⏸️ Pause capture"
+    }
+}
+
+// Function to show the state of the capture in the tray and send it to the window ("capture-state" event)
+pub fn publish_capture_state(app: &AppHandle) {
+    let state = capture_state();
+
+    if let Some(tray) = app.tray_by_id("main") {
+        let _ = tray.set_tooltip(Some(tray_tooltip(&state)));
+    }
+    if let Some(item) = app.try_state::<PauseMenuItem>() {
+        let _ = item.0.set_text(pause_menu_label(state.paused));
+    }
+    let _ = app.emit("capture-state", &state);
+}
@@ -0,0 +1,94 @@
+use std::{sync::mpsc, thread};
+
+use clipboard_rs::{Clipboard, ClipboardContext};
+use once_cell::sync::Lazy;
+use tokio::sync::oneshot;
+
+// A request sent to the hints thread, with the hints to look for and where to reply whether one matched
+type Request = (Vec<String>, oneshot::Sender<bool>);
+
+// Queue of the thread reading the formats offered by the clipboard
+// The thread owns a single clipboard context (a connection to the X server on Linux), opened on the first request
+// and kept for the next ones, so no clipboard call blocks the async runtime
+static REQUESTS: Lazy<Option<mpsc::Sender<Request>>> = Lazy::new(|| {
+    let (requests, queue) = mpsc::channel::<Request>();
+    thread::Builder::new()
+        .name("clipboard-hints".to_string())
+        .spawn(move || {
+            let mut clipboard: Option<ClipboardContext> = None;
+            for (hints, reply) in queue {
+                let _ = reply.send(check_hints(&mut clipboard, &hints));
+            }
+        })
+        .map_err(|e| eprintln!("Failed to start the clipboard hints thread: {}", e))
+        .ok()?;
+    Some(requests)
+});
+
+// Function to check if the data of a clipboard format holds a value
+// The data is compared as text (without its trailing NUL characters) and, when it is 4 bytes long, as a little
+// endian integer (Windows stores its flags as a DWORD)
+fn format_value_is(data: &[u8], value: &str) -> bool {
+    let text = String::from_utf8_lossy(data);
+    if text.trim_end_matches('\0').trim() == value {
+        return true;
+    }
+    match <[u8; 4]>::try_from(data) {
+        Ok(bytes) => u32::from_le_bytes(bytes).to_string() == value,
+        Err(_) => false,
+    }
+}
+
+// Function to check if a format offered by the clipboard matches a hint
+// A hint is either the name of a format ("x-kde-passwordManagerHint"), matched when the format is offered, or a
+// name and a value ("CanIncludeInClipboardHistory=0"), matched when the format holds that value
+fn hint_matches(clipboard: &ClipboardContext, formats: &[String], hint: &str) -> bool {
+    let (name, value) = match hint.split_once('=') {
+        Some((name, value)) => (name.trim(), Some(value.trim())),
+        None => (hint.trim(), None),
+    };
+    if !formats.iter().any(|format| format == name) {
+        return false;
+    }
+    match value {
+        Some(value) => clipboard
+            .get_buffer(name)
+            .is_ok_and(|data| format_value_is(&data, value)),
+        None => true,
+    }
+}
+
+// Function to check the hints against the formats offered by the clipboard, on the hints thread
+// The context is opened again on the next request when the formats can't be read (lost connection)
+fn check_hints(clipboard: &mut Option<ClipboardContext>, hints: &[String]) -> bool {
+    if clipboard.is_none() {
+        *clipboard = ClipboardContext::new().ok();
+    }
+    let Some(context) = clipboard.as_ref() else {
+        return false;
+    };
+    let Ok(formats) = context.available_formats() else {
+        *clipboard = None;
+        return false;
+    };
+    hints
+        .iter()
+        .any(|hint| hint_matches(context, &formats, hint))
+}
+
+// Function to check if the content of the clipboard is marked as concealed or transient by the application that
+// copied it (password managers), according to the honored hints
+// The content is recorded when the offered formats can't be read
+pub async fn marked_do_not_record(hints: Vec<String>) -> bool {
+    if hints.is_empty() {
+        return false;
+    }
+    let Some(requests) = REQUESTS.as_ref() else {
+        return false;
+    };
+    let (reply, response) = oneshot::channel();
+    if requests.send((hints, reply)).is_err() {
+        return false;
+    }
+    response.await.unwrap_or(false)
+}
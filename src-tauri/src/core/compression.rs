@@ -0,0 +1,50 @@
+use std::io::{Read, Write};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+
+use crate::core::database_api::content_hash;
+
+// Length of the beginning of a compressed text kept in plain, in bytes (what the previews and the search see)
+pub const PREVIEW_BYTES: usize = 64 * 1024;
+
+// Struct to represent a text compressed before it is stored
+pub struct CompressedText {
+    pub preview: String, // Beginning of the text, stored in plain
+    pub data: Vec<u8>,   // Whole text, compressed with deflate
+    pub hash: String,    // Hash of the whole text, so that copying it again is deduplicated
+}
+
+// Function to get the longest beginning of a text that fits in `max_bytes` without cutting a character
+pub fn text_prefix(text: &str, max_bytes: usize) -> &str {
+    let mut end = max_bytes.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+// Function to compress a text, along with what is stored next to it
+pub fn compress_text(text: &str) -> Result<CompressedText, String> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(text.as_bytes())
+        .map_err(|e| format!("Failed to compress the text: {}", e))?;
+    let data = encoder
+        .finish()
+        .map_err(|e| format!("Failed to compress the text: {}", e))?;
+
+    Ok(CompressedText {
+        preview: text_prefix(text, PREVIEW_BYTES).to_string(),
+        data,
+        hash: content_hash(text),
+    })
+}
+
+// Function to decompress a text compressed by `compress_text`
+pub fn decompress_text(data: &[u8]) -> Result<String, String> {
+    let mut text = String::new();
+    DeflateDecoder::new(data)
+        .read_to_string(&mut text)
+        .map_err(|e| format!("Failed to decompress the text: {}", e))?;
+    Ok(text)
+}
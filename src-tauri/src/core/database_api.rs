@@ -1,187 +1,354 @@
-use std::{fs, path::PathBuf, sync::Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use directories::BaseDirs;
-use once_cell::sync::OnceCell;
-use rusqlite::Connection;
+use rusqlite::{params_from_iter, types::Value, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
 
-use crate::structures::{
-    clipboard_entry::ClipboardEntry,
-    config::{config, AppConfig},
+use crate::{
+    core::{
+        blob_store::{BlobStore, ImageBlob},
+        compression::{decompress_text, CompressedText},
+        errors::DatabaseError,
+    },
+    structures::{
+        clipboard_entry::ClipboardEntry,
+        collection::Collection,
+        config::{AppConfig, RetentionLimits},
+        entry_filters::EntryFilters,
+        entry_marks::EntryMarks,
+        entry_page::{EntryPage, EntrySort, ListCursor},
+        operation::{Operation, OperationKind},
+        search_result::{SearchResult, SnippetPart},
+        source_app::SourceApp,
+    },
 };
 
-pub static DATABASE_CONNECTION: OnceCell<Mutex<Connection>> = OnceCell::new(); // Database connection
+// Markers used by SQLite to delimit the matches in search snippets (private use characters)
+const HIGHLIGHT_START: char = '\u{E000}';
+const HIGHLIGHT_END: char = '\u{E001}';
 
-// Function to get the database path based on the operating system
-fn get_db_path() -> PathBuf {
-    let base_dirs = BaseDirs::new().expect("Unable to access directories");
+// Maximum number of results returned by a search
+const SEARCH_LIMIT: i64 = 200;
 
-    // Détermine l'emplacement basé sur le système d'exploitation
-    if cfg!(target_os = "windows") {
-        // Sur Windows, utiliser AppData
-        base_dirs
-            .data_local_dir()
-            .join("PetchouSoftware")
-            .join("TactiClip")
-            .join("database.db")
-    } else {
-        // Sur macOS et Linux, utiliser .local
-        base_dirs
-            .home_dir()
-            .join(".local")
-            .join("PetchouSoftware")
-            .join("TactiClip")
-            .join("database.db")
-    }
-}
+// Half-life of an event in the frecency score, in days (a use counts half as much a week later)
+const FRECENCY_HALF_LIFE_DAYS: f64 = 7.0;
 
-// Function to initialize the database
-pub fn init_db() {
-    // Get the database path
-    let db_path = get_db_path();
-    print!("DB path: {:?}", db_path);
+// Number of operations kept in the journal, older ones can't be undone
+const JOURNAL_SIZE: i64 = 50;
 
-    // Create all directories leading to the database file
-    if let Some(parent) = db_path.parent() {
-        fs::create_dir_all(parent).expect("Failed to create directories");
-    }
+// Maximum length of the title and of the note of an entry, in characters
+const MAX_TITLE_LENGTH: usize = 200;
+const MAX_NOTE_LENGTH: usize = 10_000;
 
-    // Open the database connection
-    let conn = Connection::open(db_path).expect("Failed to open DB");
+// Share of free pages of the database file above which pruning gives the space back to the file system
+const VACUUM_FREE_PAGES_RATIO: f64 = 0.25;
 
-    // Create the table if it doesn't exist
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS clipboard_entries (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            type TEXT NOT NULL,
-            content TEXT NOT NULL,
-            added_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-            pinned INTEGER NOT NULL DEFAULT 0,
-            forced_language TEXT DEFAULT NULL
-        )",
-        [],
-    )
-    .expect("Failed to create table");
+// Separator of the tags aggregated in a single column (unit separator, never part of a tag)
+pub const TAG_SEPARATOR: char = '\u{1F}';
 
-    let columns = vec![
-        "type TEXT NOT NULL",
-        "content TEXT NOT NULL",
-        "added_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP",
-        "pinned INTEGER NOT NULL DEFAULT 0",
-        "forced_language TEXT DEFAULT NULL",
-    ];
+// Columns read by `entry_from_row`, in order (on the `clipboard_entries` table aliased as `e`)
+const ENTRY_COLUMNS: &str =
+    "e.id, e.type, e.content, e.added_at, e.pinned, e.forced_language, e.blob_hash,
+    e.paste_count, e.last_used_at, e.source_class, e.source_title, e.source_process, e.title, e.note,
+    e.is_template, e.sensitive, e.masked_content, e.expires_at";
 
-    {
-        // Scoped block pour forcer le drop de stmt avant le move
-        let query = "PRAGMA table_info(clipboard_entries)";
-        let mut stmt = conn.prepare(query).expect("Failed to prepare statement");
-        let column_info: Vec<String> = stmt
-            .query_map([], |row| row.get::<_, String>(1))
-            .expect("Failed to query columns")
-            .filter_map(Result::ok)
-            .collect();
-
-        for column in columns {
-            let column_name = column.split_whitespace().next().unwrap();
-            if !column_info.contains(&column_name.to_string()) {
-                let add_column_query =
-                    format!("ALTER TABLE clipboard_entries ADD COLUMN {}", column);
-                conn.execute(&add_column_query, [])
-                    .expect("Failed to add missing column");
-                println!("Added missing column: {}", column);
-            }
-        }
-    } // <- stmt est droppé ici
+// Tags and collections of the entry, read by `entry_from_row` right after ENTRY_COLUMNS
+const LINK_COLUMNS: &str =
+    "(SELECT group_concat(t.tag, char(31)) FROM entry_tags t WHERE t.entry_id = e.id),
+    (SELECT group_concat(c.collection_id) FROM entry_collections c WHERE c.entry_id = e.id)";
+
+// Function to build an entry from a row selected with ENTRY_COLUMNS and LINK_COLUMNS
+// Images are loaded from the blob store
+fn entry_from_row(row: &rusqlite::Row, blobs: &BlobStore) -> rusqlite::Result<ClipboardEntry> {
+    let entry_type: String = row.get(1)?;
+    let blob_hash: Option<String> = row.get(6)?;
 
-    // Maintenant on peut move `conn`
-    DATABASE_CONNECTION
-        .set(Mutex::new(conn))
-        .expect("Failed to set database connection");
+    let content = match blob_hash {
+        Some(hash) if entry_type == "image" => blobs.load_image_base64(&hash).unwrap_or_default(),
+        _ => row.get(2)?,
+    };
+
+    let source = SourceApp {
+        class: row.get(9)?,
+        title: row.get(10)?,
+        process: row.get(11)?,
+    };
+
+    let mut tags: Vec<String> = row
+        .get::<_, Option<String>>(18)?
+        .map(|tags| tags.split(TAG_SEPARATOR).map(str::to_string).collect())
+        .unwrap_or_default();
+    tags.sort();
+
+    let collections: Vec<i64> = row
+        .get::<_, Option<String>>(19)?
+        .map(|ids| ids.split(',').filter_map(|id| id.parse().ok()).collect())
+        .unwrap_or_default();
+
+    Ok(ClipboardEntry {
+        id: row.get(0)?,
+        entry_type,
+        content,
+        added_at: row.get::<_, String>(3)?,
+        pinned: row.get::<_, i32>(4)? != 0,
+        forced_language: row.get::<_, Option<String>>(5).unwrap_or(None),
+        paste_count: row.get(7)?,
+        last_used_at: row.get(8)?,
+        tags,
+        collections,
+        source: (!source.is_empty()).then_some(source),
+        title: row.get(12)?,
+        note: row.get(13)?,
+        is_template: row.get::<_, i32>(14)? != 0,
+        sensitive: row.get(15)?,
+        masked_content: row.get(16)?,
+        expires_at: row.get(17)?,
+    })
 }
 
 // Function to get the last item copied of a specific type
-pub fn get_last_item_copied(item_type: &str) -> Option<ClipboardEntry> {
-    let conn = DATABASE_CONNECTION
-        .get()
-        .expect("DB non initialisée")
-        .lock()
-        .unwrap();
-
-    let entry: ClipboardEntry = conn.query_row(
-        "SELECT id, type, content, added_at, pinned, forced_language FROM clipboard_entries WHERE type = ?1 ORDER BY added_at DESC LIMIT 1",
-        [item_type],
-        |row| {
-            Ok(ClipboardEntry {
-                id: row.get(0)?,
-                entry_type: row.get(1)?,
-                content: row.get(2)?,
-                added_at: row.get::<_, String>(3)?,
-                pinned: row.get::<_, i32>(4)? != 0,
-                forced_language: row.get::<_, Option<String>>(5).unwrap_or(None),
-            })
-        },
-    ).ok()?;
-    Some(entry)
+pub fn get_last_item_copied(
+    conn: &Connection,
+    blobs: &BlobStore,
+    item_type: &str,
+) -> Result<Option<ClipboardEntry>, DatabaseError> {
+    let entry = conn
+        .query_row(
+            &format!(
+                "SELECT {}, {} FROM clipboard_entries e
+                WHERE e.type = ?1 AND e.deleted_at IS NULL
+                ORDER BY e.added_at DESC LIMIT 1",
+                ENTRY_COLUMNS, LINK_COLUMNS
+            ),
+            [item_type],
+            |row| entry_from_row(row, blobs),
+        )
+        .optional()?;
+    entry
+        .map(|entry| with_full_content(conn, entry))
+        .transpose()
+}
+
+// Function to get the frecency score of a single event happening `days` after 1970
+// The frecency of an entry is log2 of the sum of 2^(days / half-life) over its events (its capture and every use),
+// so comparing two entries never depends on the current time and the score can be stored and indexed
+pub fn frecency_event(days: f64) -> f64 {
+    days / FRECENCY_HALF_LIFE_DAYS
+}
+
+// Function to add an event to a frecency score (log2(2^score + 2^event) without overflowing)
+fn add_frecency_event(score: f64, event: f64) -> f64 {
+    let (high, low) = if score >= event {
+        (score, event)
+    } else {
+        (event, score)
+    };
+    high + (1.0 + (low - high).exp2()).log2()
+}
+
+// Function to get the frecency score of an event happening now
+fn frecency_now() -> f64 {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() / 86400.0)
+        .unwrap_or_default();
+    frecency_event(days)
+}
+
+// Function to compute the hash identifying the content of an entry
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
 }
 
 // Function to insert a new clipboard entry into the database
-pub fn insert_clipboard_entry(entry_type: &str, content: &str, pinned: i32) -> i64 {
-    let conn = DATABASE_CONNECTION
-        .get()
-        .expect("DB non initialisée")
-        .lock()
-        .unwrap();
-    let _res = conn
-        .execute(
-            "INSERT INTO clipboard_entries (type, content, pinned) VALUES (?1, ?2, ?3)",
-            &[&entry_type, &content, &(*pinned.to_string())],
-        )
-        .expect("Failed to insert clipboard entry");
+// Returns the ID of the entry and whether it already existed (with `deduplicate`, the existing entry is
+// moved to the top of the history instead of inserting a duplicate)
+pub fn insert_clipboard_entry(
+    conn: &Connection,
+    entry_type: &str,
+    content: &str,
+    pinned: i32,
+    source: Option<&SourceApp>,
+    marks: &EntryMarks,
+    deduplicate: bool,
+) -> Result<(i64, bool), DatabaseError> {
+    insert_entry(
+        conn,
+        entry_type,
+        content,
+        pinned,
+        &content_hash(content),
+        None,
+        source,
+        marks,
+        deduplicate,
+    )
+}
 
-    // Return the ID of the inserted entry
-    conn.last_insert_rowid()
-}
-
-// Function to get all clipboard entries from the database
-pub fn get_all_ids() -> Vec<i64> {
-    // Get the database connection
-    let conn = DATABASE_CONNECTION
-        .get()
-        .expect("DB not initialized")
-        .lock()
-        .unwrap();
-    let mut stmt = conn
-        .prepare("SELECT id FROM clipboard_entries ORDER BY added_at DESC")
-        .unwrap();
-    let ids_iter = stmt.query_map([], |row| row.get(0)).unwrap();
-    let ids: Vec<i64> = ids_iter.filter_map(Result::ok).collect();
+// Function to insert a text too large to be stored in plain, compressed beforehand by `compress_text`
+// Only the beginning of the text is stored in `content`, `get_item_by_id` gives the whole text back
+pub fn insert_compressed_clipboard_entry(
+    conn: &mut Connection,
+    entry_type: &str,
+    text: &CompressedText,
+    pinned: i32,
+    source: Option<&SourceApp>,
+    marks: &EntryMarks,
+    deduplicate: bool,
+) -> Result<(i64, bool), DatabaseError> {
+    // The entry is never visible without its compressed text
+    let tx = conn.transaction()?;
+    let (id, existed) = insert_entry(
+        &tx,
+        entry_type,
+        &text.preview,
+        pinned,
+        &text.hash,
+        None,
+        source,
+        marks,
+        deduplicate,
+    )?;
 
-    ids
+    // A deduplicated entry already holds the same text
+    if !existed {
+        tx.execute(
+            "UPDATE clipboard_entries
+            SET content_encoding = 'deflate', compressed_content = ?1, byte_size = ?2
+            WHERE id = ?3",
+            (&text.data, text.data.len() as i64, id),
+        )?;
+    }
+    tx.commit()?;
+
+    Ok((id, existed))
 }
 
-// Function to get a clipboard entry by its ID and truncate the content if necessary
-pub fn get_truncated_item_by_id(id: i64) -> Option<ClipboardEntry> {
-    let conn = DATABASE_CONNECTION
-        .get()
-        .expect("DB not initialized")
-        .lock()
-        .unwrap();
-    let configuration: AppConfig = config();
-
-    let entry: ClipboardEntry = conn.query_row(
-        "SELECT id, type, content, added_at, pinned, forced_language FROM clipboard_entries WHERE id = ?1",
-        [id],
-        |row| {
-            Ok(ClipboardEntry {
-                id: row.get(0)?,
-                entry_type: row.get(1)?,
-                content: row.get(2)?,
-                added_at: row.get(3)?,
-                pinned: row.get::<_, i32>(4)? != 0,
-                forced_language: row.get::<_, Option<String>>(5).unwrap_or(None),
-            })
-        },
-    ).ok().expect("Failed to get clipboard entry.");
+// Function to insert a new image entry, the image itself goes to the blob store
+pub fn insert_image_entry(
+    conn: &Connection,
+    blobs: &BlobStore,
+    base64_image: &str,
+    pinned: i32,
+    source: Option<&SourceApp>,
+    marks: &EntryMarks,
+    deduplicate: bool,
+) -> Result<(i64, bool), DatabaseError> {
+    // Runs on the writer, so the blob can't be collected before the entry references it
+    let blob = blobs
+        .store_image(base64_image)
+        .map_err(DatabaseError::Blob)?;
+    insert_entry(
+        conn,
+        "image",
+        "",
+        pinned,
+        &blob.hash,
+        Some(&blob),
+        source,
+        marks,
+        deduplicate,
+    )
+}
+
+// Function to insert an entry, or move the existing one to the top when deduplication is enabled
+// The marks are written by the same statement, so a secret is never visible unmasked
+#[allow(clippy::too_many_arguments)]
+fn insert_entry(
+    conn: &Connection,
+    entry_type: &str,
+    content: &str,
+    pinned: i32,
+    hash: &str,
+    image: Option<&ImageBlob>,
+    source: Option<&SourceApp>,
+    marks: &EntryMarks,
+    deduplicate: bool,
+) -> Result<(i64, bool), DatabaseError> {
+    let source = source.cloned().unwrap_or_default();
+    let expiry_minutes = marks.expiry_minutes.map(|m| m.max(0));
+
+    if deduplicate {
+        let existing: Option<(i64, f64)> = conn
+            .query_row(
+                "SELECT id, frecency FROM clipboard_entries
+                WHERE content_hash = ?1 AND type = ?2 AND deleted_at IS NULL
+                ORDER BY added_at DESC LIMIT 1",
+                [hash, entry_type],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        // Copying the same content again counts as a use, the entry now comes from the new application and
+        // gets the marks and the lifetime of the new copy
+        if let Some((id, frecency)) = existing {
+            conn.execute(
+                "UPDATE clipboard_entries
+                SET added_at = CURRENT_TIMESTAMP, frecency = ?1,
+                    source_class = ?2, source_title = ?3, source_process = ?4,
+                    sensitive = ?5, masked_content = ?6,
+                    expires_at = CASE WHEN ?7 IS NULL THEN NULL
+                        ELSE datetime('now', '+' || ?7 || ' minutes') END
+                WHERE id = ?8",
+                (
+                    add_frecency_event(frecency, frecency_now()),
+                    &source.class,
+                    &source.title,
+                    &source.process,
+                    &marks.sensitive,
+                    &marks.masked_content,
+                    expiry_minutes,
+                    id,
+                ),
+            )?;
+            return Ok((id, true));
+        }
+    }
+
+    conn.execute(
+            "INSERT INTO clipboard_entries
+                (type, content, pinned, content_hash, byte_size, blob_hash, image_width, image_height, mime_type, frecency,
+                source_class, source_title, source_process, sensitive, masked_content, expires_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15,
+                CASE WHEN ?16 IS NULL THEN NULL ELSE datetime('now', '+' || ?16 || ' minutes') END)",
+            (
+                entry_type,
+                content,
+                pinned,
+                hash,
+                image.map(|i| i.byte_size).unwrap_or(content.len() as i64),
+                image.map(|i| i.hash.as_str()),
+                image.map(|i| i.width),
+                image.map(|i| i.height),
+                image.map(|i| i.mime_type),
+                frecency_now(),
+                &source.class,
+                &source.title,
+                &source.process,
+                &marks.sensitive,
+                &marks.masked_content,
+                expiry_minutes,
+            ),
+        )?;
+
+    // Return the ID of the inserted entry
+    Ok((conn.last_insert_rowid(), false))
+}
 
+// Function to get all clipboard entries from the database (the trash excluded)
+pub fn get_all_ids(conn: &Connection) -> Result<Vec<i64>, DatabaseError> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM clipboard_entries WHERE deleted_at IS NULL ORDER BY added_at DESC",
+    )?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+
+    Ok(ids)
+}
+
+// Function to turn an entry into its preview (plain text only, truncated if necessary)
+// The title replaces the content when there is one, except for images and colors which are rendered from it
+fn to_preview(entry: ClipboardEntry, max_characters: usize) -> ClipboardEntry {
     let mut content = entry.content.clone();
 
     // Si c'est du rich_text, on extrait le plain uniquement
@@ -191,148 +358,1153 @@ pub fn get_truncated_item_by_id(id: i64) -> Option<ClipboardEntry> {
         }
     }
 
-    // Troncature si texte non image
-    if entry.entry_type != "image"
-        && content.len() > configuration.max_displayed_characters as usize
+    // Secrets never reach the window
+    if let Some(masked) = &entry.masked_content {
+        content = masked.clone();
+    }
+
+    if let Some(title) = entry
+        .title
+        .as_ref()
+        .filter(|_| entry.entry_type != "image" && entry.entry_type != "color")
     {
-        content = content
-            .chars()
-            .take(configuration.max_displayed_characters as usize)
-            .collect::<String>()
-            + "...";
-    }
-
-    Some(ClipboardEntry { content, ..entry })
-}
-
-// Function to get a clipboard entry by its ID
-pub fn get_item_by_id(id: i64) -> Option<ClipboardEntry> {
-    // Get the database connection
-    let conn = DATABASE_CONNECTION
-        .get()
-        .expect("DB non initialisée")
-        .lock()
-        .unwrap();
-    let entry: ClipboardEntry = conn.query_row(
-        "SELECT id, type, content, added_at, pinned, forced_language FROM clipboard_entries WHERE id = ?1",
-        [id],
-        |row| {
-            Ok(ClipboardEntry {
-                id: row.get(0)?,
-                entry_type: row.get(1)?,
-                content: row.get(2)?,
-                added_at: row.get(3)?,
-                pinned: row.get::<_, i32>(4)? != 0,
-                forced_language: row.get::<_, Option<String>>(5).unwrap_or(None),
-            })
-        },
-    ).ok()?;
-    Some(entry)
+        content = title.clone();
+    }
+
+    // Troncature si texte non image
+    if entry.entry_type != "image" && content.len() > max_characters {
+        content = content.chars().take(max_characters).collect::<String>() + "...";
+    }
+
+    ClipboardEntry {
+        content,
+        masked_content: None,
+        ..entry
+    }
 }
 
-// Function to delete a clipboard entry by its ID
-pub fn delete_item_by_id(id: i64) {
-    // Get the database connection
-    let conn = DATABASE_CONNECTION
-        .get()
-        .expect("DB non initialisée")
-        .lock()
-        .unwrap();
-    let _res = conn
-        .execute("DELETE FROM clipboard_entries WHERE id = ?1", [id])
-        .unwrap();
+// Function to get a clipboard entry by its ID and truncate the content if necessary
+pub fn get_truncated_item_by_id(
+    conn: &Connection,
+    blobs: &BlobStore,
+    id: i64,
+    max_characters: usize,
+) -> Result<ClipboardEntry, DatabaseError> {
+    // The entry may have been deleted since its ID was sent to the frontend
+    // The preview of a compressed text is made from its beginning, it is not decompressed
+    let entry = get_stored_item_by_id(conn, blobs, id)?;
+
+    Ok(to_preview(entry, max_characters))
 }
 
-// Function to delete all clipboard entries
-pub fn delete_all_items() -> i64 {
-    // Get the database connection
-    let conn = DATABASE_CONNECTION
-        .get()
-        .expect("DB non initialisée")
-        .lock()
-        .unwrap();
+// Function to replace the content of an entry by the whole text when it is stored compressed
+fn with_full_content(
+    conn: &Connection,
+    mut entry: ClipboardEntry,
+) -> Result<ClipboardEntry, DatabaseError> {
+    let compressed: Option<Vec<u8>> = conn
+        .query_row(
+            "SELECT compressed_content FROM clipboard_entries
+            WHERE id = ?1 AND content_encoding = 'deflate'",
+            [entry.id],
+            |row| row.get(0),
+        )
+        .optional()?;
 
-    let res = conn.execute("DELETE FROM clipboard_entries", []).unwrap();
-    if res == 0 {
-        println!("Failed to delete all clipboard entries");
+    if let Some(data) = compressed {
+        entry.content = decompress_text(&data).map_err(DatabaseError::Compression)?;
     }
+    Ok(entry)
+}
 
-    println!("Deleted {} clipboard entries", res);
+// Function to get a clipboard entry by its ID, with its whole content
+pub fn get_item_by_id(
+    conn: &Connection,
+    blobs: &BlobStore,
+    id: i64,
+) -> Result<ClipboardEntry, DatabaseError> {
+    let entry = get_stored_item_by_id(conn, blobs, id)?;
+    with_full_content(conn, entry)
+}
 
-    // Get all IDs of deleted entries
-    res as i64
+// Function to get a clipboard entry by its ID, as stored (only the beginning of a compressed text)
+fn get_stored_item_by_id(
+    conn: &Connection,
+    blobs: &BlobStore,
+    id: i64,
+) -> Result<ClipboardEntry, DatabaseError> {
+    conn.query_row(
+        &format!(
+            "SELECT {}, {} FROM clipboard_entries e WHERE e.id = ?1",
+            ENTRY_COLUMNS, LINK_COLUMNS
+        ),
+        [id],
+        |row| entry_from_row(row, blobs),
+    )
+    .optional()?
+    .ok_or(DatabaseError::NotFound(id))
 }
 
-// Function to pin an entry by its ID
-pub fn pin_item_by_id(id: i64) {
-    // Get the database connection
-    let conn = DATABASE_CONNECTION
-        .get()
-        .expect("DB non initialisée")
-        .lock()
-        .unwrap();
-    let _res = conn
-        .execute(
-            "UPDATE clipboard_entries SET pinned = 1 WHERE id = ?1",
+// Function to delete a clipboard entry by its ID, for good (`trash_item_by_id` keeps it in the trash)
+pub fn delete_item_by_id(
+    conn: &Connection,
+    blobs: &BlobStore,
+    id: i64,
+) -> Result<(), DatabaseError> {
+    let blob_hash: Option<String> = conn
+        .query_row(
+            "DELETE FROM clipboard_entries WHERE id = ?1 RETURNING blob_hash",
             [id],
+            |row| row.get(0),
         )
-        .unwrap();
+        .optional()?
+        .ok_or(DatabaseError::NotFound(id))?;
+
+    // Delete the image of the entry if no other entry uses it
+    if let Some(hash) = blob_hash {
+        blobs.remove_if_orphan(conn, &hash);
+    }
+
+    Ok(())
 }
 
-// Function to unpin an entry by its ID
-pub fn unpin_item_by_id(id: i64) {
-    // Get the database connection
-    let conn = DATABASE_CONNECTION
-        .get()
-        .expect("DB non initialisée")
-        .lock()
-        .unwrap();
-    let _res = conn
-        .execute(
-            "UPDATE clipboard_entries SET pinned = 0 WHERE id = ?1",
-            [id],
+// Function to record a destructive operation in the journal, so it can be undone
+fn record_operation(
+    conn: &Connection,
+    kind: OperationKind,
+    ids: &[i64],
+) -> Result<(), DatabaseError> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let ids = serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string());
+    conn.execute(
+        "INSERT INTO operations (kind, entry_ids) VALUES (?1, ?2)",
+        (kind.as_str(), ids),
+    )?;
+
+    // Forget the oldest operations
+    conn.execute(
+        "DELETE FROM operations WHERE id <= (SELECT MAX(id) FROM operations) - ?1",
+        [JOURNAL_SIZE],
+    )?;
+
+    Ok(())
+}
+
+// Function to collect the IDs returned by an `UPDATE ... RETURNING id` statement
+fn returned_ids(conn: &Connection, query: &str) -> rusqlite::Result<Vec<i64>> {
+    let mut stmt = conn.prepare(query)?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>();
+    ids
+}
+
+// Function to move a clipboard entry to the trash
+pub fn trash_item_by_id(conn: &mut Connection, id: i64) -> Result<(), DatabaseError> {
+    let tx = conn.transaction()?;
+
+    let trashed = tx.execute(
+        "UPDATE clipboard_entries SET deleted_at = CURRENT_TIMESTAMP
+        WHERE id = ?1 AND deleted_at IS NULL",
+        [id],
+    )?;
+    if trashed == 0 {
+        return Err(DatabaseError::NotFound(id));
+    }
+
+    record_operation(&tx, OperationKind::Delete, &[id])?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+// Function to move all clipboard entries to the trash, returns the number of trashed entries
+pub fn trash_all_items(conn: &mut Connection) -> Result<i64, DatabaseError> {
+    let tx = conn.transaction()?;
+
+    let ids = returned_ids(
+        &tx,
+        "UPDATE clipboard_entries SET deleted_at = CURRENT_TIMESTAMP
+        WHERE deleted_at IS NULL RETURNING id",
+    )?;
+    record_operation(&tx, OperationKind::DeleteAll, &ids)?;
+    tx.commit()?;
+
+    println!("Moved {} clipboard entries to the trash", ids.len());
+    Ok(ids.len() as i64)
+}
+
+// Function to restore an entry from the trash
+pub fn restore_item_by_id(conn: &Connection, id: i64) -> Result<(), DatabaseError> {
+    update_item(
+        conn,
+        id,
+        "UPDATE clipboard_entries SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NOT NULL",
+        Value::Null,
+    )
+}
+
+// Function to delete every entry of the trash for good, returns the number of deleted entries
+pub fn empty_trash(conn: &Connection, blobs: &BlobStore) -> Result<i64, DatabaseError> {
+    let res = conn.execute(
+        "DELETE FROM clipboard_entries WHERE deleted_at IS NOT NULL",
+        [],
+    )?;
+    println!("Deleted {} clipboard entries from the trash", res);
+
+    // The deletions can't be undone anymore
+    conn.execute(
+        "DELETE FROM operations WHERE kind IN (?1, ?2)",
+        (
+            OperationKind::Delete.as_str(),
+            OperationKind::DeleteAll.as_str(),
+        ),
+    )?;
+
+    // Delete the images of the deleted entries
+    blobs.collect_orphans(conn);
+
+    Ok(res as i64)
+}
+
+// Function to delete the entries that have been in the trash for more than `days` days
+pub fn purge_trash(conn: &Connection, blobs: &BlobStore, days: i64) -> Result<i64, DatabaseError> {
+    let res = conn.execute(
+        &format!(
+            "DELETE FROM clipboard_entries
+            WHERE deleted_at IS NOT NULL AND deleted_at < datetime('now', '-{} days')",
+            days.max(0)
+        ),
+        [],
+    )?;
+
+    if res > 0 {
+        blobs.collect_orphans(conn);
+        println!("Purged {} clipboard entries from the trash", res);
+    }
+
+    Ok(res as i64)
+}
+
+// Function to undo the most recent operation of the journal, returns the undone operation (None if the journal is empty)
+// Entries deleted for good since the operation are not brought back
+pub fn undo_last_operation(conn: &mut Connection) -> Result<Option<Operation>, DatabaseError> {
+    let tx = conn.transaction()?;
+
+    let last = tx
+        .query_row(
+            "SELECT id, kind, performed_at, entry_ids FROM operations ORDER BY id DESC LIMIT 1",
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            },
         )
-        .unwrap();
+        .optional()?;
+
+    let Some((id, kind, performed_at, entry_ids)) = last else {
+        return Ok(None);
+    };
+
+    // Drop the operation from the journal whether it can be reversed or not
+    tx.execute("DELETE FROM operations WHERE id = ?1", [id])?;
+
+    let Some(kind) = OperationKind::from_name(&kind) else {
+        tx.commit()?;
+        return Ok(None);
+    };
+    let entry_ids: Vec<i64> = serde_json::from_str(&entry_ids).unwrap_or_default();
+
+    {
+        let mut stmt = tx.prepare(match kind {
+            OperationKind::Delete | OperationKind::DeleteAll => {
+                "UPDATE clipboard_entries SET deleted_at = NULL WHERE id = ?1"
+            }
+            OperationKind::UnpinAll => "UPDATE clipboard_entries SET pinned = 1 WHERE id = ?1",
+        })?;
+        for entry_id in &entry_ids {
+            stmt.execute([entry_id])?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(Some(Operation {
+        id,
+        kind,
+        performed_at,
+        entry_ids,
+    }))
+}
+
+// Function to update a single entry, fails if the entry doesn't exist
+fn update_item(conn: &Connection, id: i64, query: &str, value: Value) -> Result<(), DatabaseError> {
+    match conn.execute(query, (value, id))? {
+        0 => Err(DatabaseError::NotFound(id)),
+        _ => Ok(()),
+    }
+}
+
+// Function to pin an entry by its ID
+pub fn pin_item_by_id(conn: &Connection, id: i64) -> Result<(), DatabaseError> {
+    update_item(
+        conn,
+        id,
+        "UPDATE clipboard_entries SET pinned = ?1 WHERE id = ?2",
+        Value::Integer(1),
+    )
+}
+
+// Function to unpin an entry by its ID
+pub fn unpin_item_by_id(conn: &Connection, id: i64) -> Result<(), DatabaseError> {
+    update_item(
+        conn,
+        id,
+        "UPDATE clipboard_entries SET pinned = ?1 WHERE id = ?2",
+        Value::Integer(0),
+    )
 }
 
-// Function to unpin all entries
-pub fn unpin_all_items() -> i64 {
-    // Get the database connection
-    let conn = DATABASE_CONNECTION
-        .get()
-        .expect("DB non initialisée")
-        .lock()
-        .unwrap();
-    let res = conn
-        .execute("UPDATE clipboard_entries SET pinned = 0", [])
-        .unwrap();
+// Function to unpin all entries (the trash excluded)
+pub fn unpin_all_items(conn: &mut Connection) -> Result<i64, DatabaseError> {
+    let tx = conn.transaction()?;
+
+    let ids = returned_ids(
+        &tx,
+        "UPDATE clipboard_entries SET pinned = 0
+        WHERE pinned = 1 AND deleted_at IS NULL RETURNING id",
+    )?;
+    record_operation(&tx, OperationKind::UnpinAll, &ids)?;
+    tx.commit()?;
 
     // Return the number of rows affected
-    res as i64
+    Ok(ids.len() as i64)
 }
 
 // Function to set the forced language of an entry by its ID
-pub fn set_forced_language(id: i64, language: &str) {
-    // Get the database connection
-    let conn = DATABASE_CONNECTION
-        .get()
-        .expect("DB non initialisée")
-        .lock()
-        .unwrap();
-
-    // Convert the id to a string
-    let id = id.to_string();
-
-    let res = conn
-        .execute(
-            "UPDATE clipboard_entries SET forced_language = ?1 WHERE id = ?2",
-            [language, &id],
+pub fn set_forced_language(
+    conn: &Connection,
+    id: i64,
+    language: &str,
+) -> Result<(), DatabaseError> {
+    update_item(
+        conn,
+        id,
+        "UPDATE clipboard_entries SET forced_language = ?1 WHERE id = ?2",
+        Value::Text(language.to_string()),
+    )
+}
+
+// Function to mark an entry as a template (its placeholders are expanded when it is pushed to the clipboard)
+pub fn set_item_template(
+    conn: &Connection,
+    id: i64,
+    is_template: bool,
+) -> Result<(), DatabaseError> {
+    update_item(
+        conn,
+        id,
+        "UPDATE clipboard_entries SET is_template = ?1 WHERE id = ?2",
+        Value::Integer(is_template as i64),
+    )
+}
+
+// Function to give an entry a lifetime in minutes (None keeps it forever), returns its new expiry date
+pub fn set_item_expiry(
+    conn: &Connection,
+    id: i64,
+    minutes: Option<i64>,
+) -> Result<Option<String>, DatabaseError> {
+    if minutes.is_some_and(|m| m < 1) {
+        return Err(DatabaseError::InvalidInput(
+            "The lifetime of an entry must be at least a minute".to_string(),
+        ));
+    }
+
+    conn.query_row(
+        "UPDATE clipboard_entries
+        SET expires_at = CASE WHEN ?1 IS NULL THEN NULL ELSE datetime('now', '+' || ?1 || ' minutes') END
+        WHERE id = ?2 RETURNING expires_at",
+        (minutes, id),
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or(DatabaseError::NotFound(id))
+}
+
+// Function to delete the entries whose expiry date has passed (bypassing the trash), returns their IDs
+// Pinned entries never expire, as they are never pruned
+pub fn purge_expired_items(
+    conn: &Connection,
+    blobs: &BlobStore,
+) -> Result<Vec<i64>, DatabaseError> {
+    let ids = returned_ids(
+        conn,
+        "DELETE FROM clipboard_entries
+        WHERE expires_at IS NOT NULL AND expires_at <= CURRENT_TIMESTAMP AND pinned = 0 RETURNING id",
+    )?;
+
+    if !ids.is_empty() {
+        blobs.collect_orphans(conn);
+        println!("Deleted {} expired clipboard entries", ids.len());
+    }
+
+    Ok(ids)
+}
+
+// Function to clean a title or a note written by the user, None when it is blank (the value is removed)
+fn user_text(text: Option<&str>, max_length: usize, what: &str) -> Result<Value, DatabaseError> {
+    let Some(text) = text.map(str::trim).filter(|t| !t.is_empty()) else {
+        return Ok(Value::Null);
+    };
+    if text.chars().count() > max_length {
+        return Err(DatabaseError::InvalidInput(format!(
+            "The {} can't be longer than {} characters",
+            what, max_length
+        )));
+    }
+    Ok(Value::Text(text.to_string()))
+}
+
+// Function to set the title of an entry by its ID, a blank title removes it
+pub fn set_item_title(
+    conn: &Connection,
+    id: i64,
+    title: Option<&str>,
+) -> Result<(), DatabaseError> {
+    update_item(
+        conn,
+        id,
+        "UPDATE clipboard_entries SET title = ?1 WHERE id = ?2",
+        user_text(title, MAX_TITLE_LENGTH, "title")?,
+    )
+}
+
+// Function to set the note of an entry by its ID, a blank note removes it
+pub fn set_item_note(conn: &Connection, id: i64, note: Option<&str>) -> Result<(), DatabaseError> {
+    update_item(
+        conn,
+        id,
+        "UPDATE clipboard_entries SET note = ?1 WHERE id = ?2",
+        user_text(note, MAX_NOTE_LENGTH, "note")?,
+    )
+}
+
+// Function to build the SQL conditions matching a set of filters (on the `clipboard_entries` table aliased as `e`)
+pub fn filters_to_sql(filters: &EntryFilters) -> (Vec<String>, Vec<Value>) {
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<Value> = Vec::new();
+
+    conditions.push(match filters.trashed {
+        true => "e.deleted_at IS NOT NULL".to_string(),
+        false => "e.deleted_at IS NULL".to_string(),
+    });
+
+    if let Some(entry_type) = &filters.entry_type {
+        params.push(Value::Text(entry_type.clone()));
+        conditions.push(format!("e.type = ?{}", params.len()));
+    }
+
+    if let Some(pinned) = filters.pinned {
+        params.push(Value::Integer(pinned as i64));
+        conditions.push(format!("e.pinned = ?{}", params.len()));
+    }
+
+    if let Some(template) = filters.template {
+        params.push(Value::Integer(template as i64));
+        conditions.push(format!("e.is_template = ?{}", params.len()));
+    }
+
+    if let Some(language) = &filters.language {
+        params.push(Value::Text(language.clone()));
+        conditions.push(format!("lower(e.forced_language) = ?{}", params.len()));
+    }
+
+    if let Some(after) = &filters.added_after {
+        params.push(Value::Text(after.clone()));
+        conditions.push(format!("e.added_at >= datetime(?{})", params.len()));
+    }
+
+    if let Some(before) = &filters.added_before {
+        // A date without time includes the whole day
+        let before = if before.len() == 10 {
+            format!("{} 23:59:59", before)
+        } else {
+            before.clone()
+        };
+        params.push(Value::Text(before));
+        conditions.push(format!("e.added_at <= datetime(?{})", params.len()));
+    }
+
+    if let Some(collection) = &filters.collection {
+        params.push(Value::Text(collection.clone()));
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM entry_collections ec JOIN collections c ON c.id = ec.collection_id
+                WHERE ec.entry_id = e.id AND c.name = ?{})",
+            params.len()
+        ));
+    }
+
+    if let Some(app) = &filters.app {
+        params.push(Value::Text(app.clone()));
+        conditions.push(format!(
+            "(instr(lower(e.source_class), ?{n}) > 0 OR instr(lower(e.source_process), ?{n}) > 0)",
+            n = params.len()
+        ));
+    }
+
+    if let Some(window) = &filters.window {
+        params.push(Value::Text(window.clone()));
+        conditions.push(format!(
+            "instr(lower(e.source_title), ?{}) > 0",
+            params.len()
+        ));
+    }
+
+    if let Some(tag) = &filters.tag {
+        params.push(Value::Text(tag.clone()));
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM entry_tags t WHERE t.entry_id = e.id AND t.tag = ?{})",
+            params.len()
+        ));
+    }
+
+    (conditions, params)
+}
+
+// Function to turn free text into a FTS5 query (every word must match, the last one as a prefix)
+fn to_fts_query(text: &str) -> String {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+
+    match words.len() {
+        0 => String::new(),
+        _ => words.join(" ") + "*",
+    }
+}
+
+// Function to split a snippet returned by SQLite into highlighted and regular parts
+fn split_snippet(snippet: &str) -> Vec<SnippetPart> {
+    let mut parts: Vec<SnippetPart> = Vec::new();
+    let mut current = String::new();
+    let mut highlighted = false;
+
+    for c in snippet.chars() {
+        if c == HIGHLIGHT_START || c == HIGHLIGHT_END {
+            if !current.is_empty() {
+                parts.push(SnippetPart {
+                    text: std::mem::take(&mut current),
+                    highlighted,
+                });
+            }
+            highlighted = c == HIGHLIGHT_START;
+        } else {
+            current.push(c);
+        }
+    }
+
+    if !current.is_empty() {
+        parts.push(SnippetPart {
+            text: current,
+            highlighted,
+        });
+    }
+
+    parts
+}
+
+// Function to search the clipboard entries, returns ranked ids with highlighted snippets
+pub fn search_items(
+    conn: &Connection,
+    text: &str,
+    filters: &EntryFilters,
+    preview_length: usize,
+) -> Result<Vec<SearchResult>, DatabaseError> {
+    let (mut conditions, mut params) = filters_to_sql(filters);
+    let fts_query = to_fts_query(text);
+
+    let query = if fts_query.is_empty() {
+        // Only filters, list the matching entries from the most recent one
+        params.push(Value::Integer(preview_length as i64));
+        let preview_param = params.len();
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        format!(
+            "SELECT e.id, 0.0,
+                CASE WHEN e.title IS NOT NULL THEN e.title WHEN e.type = 'image' THEN ''
+                    ELSE substr(COALESCE(e.masked_content, e.content), 1, ?{p}) END
+            FROM clipboard_entries e
+            {}
+            ORDER BY e.added_at DESC
+            LIMIT {}",
+            where_clause,
+            SEARCH_LIMIT,
+            p = preview_param
         )
-        .unwrap();
-    if res == 0 {
-        println!(
-            "Failed to set forced language for clipboard entry with ID: {}",
-            id
+    } else {
+        params.push(Value::Integer(preview_length as i64));
+        let preview_param = params.len();
+        params.push(Value::Text(fts_query));
+        conditions.push(format!("clipboard_entries_fts MATCH ?{}", params.len()));
+        // The snippet would reveal the secrets of the masked entries, their masked content is shown instead
+        format!(
+            "SELECT e.id, bm25(clipboard_entries_fts),
+                CASE WHEN e.masked_content IS NOT NULL THEN COALESCE(e.title, substr(e.masked_content, 1, ?{p}))
+                ELSE snippet(clipboard_entries_fts, -1, '{}', '{}', '…', 24) END
+            FROM clipboard_entries_fts
+            JOIN clipboard_entries e ON e.id = clipboard_entries_fts.rowid
+            WHERE {}
+            ORDER BY bm25(clipboard_entries_fts), e.added_at DESC
+            LIMIT {}",
+            HIGHLIGHT_START,
+            HIGHLIGHT_END,
+            conditions.join(" AND "),
+            SEARCH_LIMIT,
+            p = preview_param
+        )
+    };
+
+    let mut stmt = conn.prepare(&query)?;
+    let results = stmt
+        .query_map(params_from_iter(params), |row| {
+            Ok(SearchResult {
+                id: row.get(0)?,
+                rank: row.get(1)?,
+                snippet: split_snippet(&row.get::<_, String>(2)?),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<SearchResult>>>()?;
+
+    Ok(results)
+}
+
+// Function to collect the ids of the unpinned entries exceeding a set of retention limits
+// When `entry_type` is set, only the entries of this type are considered
+fn entries_exceeding_limits(
+    conn: &Connection,
+    limits: &RetentionLimits,
+    entry_type: Option<&str>,
+) -> rusqlite::Result<Vec<i64>> {
+    let type_condition = if entry_type.is_some() {
+        "AND type = ?1"
+    } else {
+        ""
+    };
+    let type_param: Vec<&str> = entry_type.into_iter().collect();
+    let mut ids: Vec<i64> = Vec::new();
+
+    // Entries older than the maximum age
+    if let Some(max_age_days) = limits.max_age_days {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id FROM clipboard_entries
+            WHERE pinned = 0 AND deleted_at IS NULL {} AND added_at < datetime('now', '-{} days')",
+            type_condition,
+            max_age_days.max(0)
+        ))?;
+        ids.extend(
+            stmt.query_map(params_from_iter(&type_param), |row| row.get::<_, i64>(0))?
+                .filter_map(Result::ok),
         );
     }
+
+    // Oldest entries beyond the maximum count
+    if let Some(max_entries) = limits.max_entries {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id FROM clipboard_entries
+            WHERE pinned = 0 AND deleted_at IS NULL {}
+            ORDER BY added_at DESC, id DESC
+            LIMIT -1 OFFSET {}",
+            type_condition,
+            max_entries.max(0)
+        ))?;
+        ids.extend(
+            stmt.query_map(params_from_iter(&type_param), |row| row.get::<_, i64>(0))?
+                .filter_map(Result::ok),
+        );
+    }
+
+    // Oldest entries beyond the maximum size (pinned entries take their share of the space first)
+    if let Some(max_size_mb) = limits.max_size_mb {
+        let max_size = max_size_mb.max(0) * 1024 * 1024;
+        let pinned_size: i64 = conn.query_row(
+            &format!(
+                "SELECT COALESCE(SUM(byte_size), 0) FROM clipboard_entries
+                WHERE pinned = 1 AND deleted_at IS NULL {}",
+                type_condition
+            ),
+            params_from_iter(&type_param),
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id FROM (
+                SELECT id, SUM(byte_size) OVER (ORDER BY added_at DESC, id DESC) AS total
+                FROM clipboard_entries
+                WHERE pinned = 0 AND deleted_at IS NULL {}
+            ) WHERE total > {}",
+            type_condition,
+            max_size - pinned_size
+        ))?;
+        ids.extend(
+            stmt.query_map(params_from_iter(&type_param), |row| row.get::<_, i64>(0))?
+                .filter_map(Result::ok),
+        );
+    }
+
+    Ok(ids)
+}
+
+// Function to get the share of the pages of the database file that are free
+fn free_pages_ratio(conn: &Connection) -> rusqlite::Result<f64> {
+    conn.query_row(
+        "SELECT CAST(f.freelist_count AS REAL) / MAX(p.page_count, 1)
+        FROM pragma_freelist_count() f, pragma_page_count() p",
+        [],
+        |row| row.get(0),
+    )
+}
+
+// Function to delete the entries exceeding the retention policy, returns the ids of the deleted entries
+pub fn prune_items(
+    conn: &mut Connection,
+    blobs: &BlobStore,
+    configuration: &AppConfig,
+) -> Result<Vec<i64>, DatabaseError> {
+    let global_limits = RetentionLimits {
+        max_entries: configuration.retention_max_entries,
+        max_age_days: configuration.retention_max_age_days,
+        max_size_mb: configuration.retention_max_database_size_mb,
+    };
+
+    let mut ids: Vec<i64> = Vec::new();
+    let mut size_pruned = global_limits.max_size_mb.is_some();
+
+    match entries_exceeding_limits(conn, &global_limits, None) {
+        Ok(found) => ids.extend(found),
+        Err(e) => eprintln!("Failed to apply the retention policy: {}", e),
+    }
+
+    for (entry_type, limits) in &configuration.retention_per_type {
+        size_pruned |= limits.max_size_mb.is_some();
+        match entries_exceeding_limits(conn, limits, Some(entry_type)) {
+            Ok(found) => ids.extend(found),
+            Err(e) => eprintln!(
+                "Failed to apply the retention policy for {}: {}",
+                entry_type, e
+            ),
+        }
+    }
+
+    ids.sort_unstable();
+    ids.dedup();
+
+    if ids.is_empty() {
+        return Ok(ids);
+    }
+
+    // Delete everything at once so the history is never left partially pruned
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare("DELETE FROM clipboard_entries WHERE id = ?1 AND pinned = 0")?;
+        for id in &ids {
+            stmt.execute([id])?;
+        }
+    }
+    tx.commit()?;
+
+    // Delete the images of the pruned entries
+    blobs.collect_orphans(conn);
+
+    // Give the space back to the file system when the size of the database is limited, only once enough pages
+    // are free as it rewrites the whole file
+    if size_pruned && free_pages_ratio(conn).is_ok_and(|ratio| ratio >= VACUUM_FREE_PAGES_RATIO) {
+        let _ = conn.execute_batch("VACUUM");
+    }
+
+    println!("Pruned {} clipboard entries", ids.len());
+
+    Ok(ids)
+}
+
+// Function to get the SQL expression and direction used to sort the entries
+fn sort_to_sql(sort: EntrySort) -> (&'static str, bool) {
+    // (expression, descending)
+    match sort {
+        EntrySort::Date => ("e.added_at", true),
+        EntrySort::Type => ("e.type", false),
+        EntrySort::Size => ("e.byte_size", true),
+        EntrySort::Usage => ("e.paste_count", true),
+        EntrySort::Frecency => ("e.frecency", true),
+    }
+}
+
+// Function to get a page of entry previews, pinned entries first
+pub fn list_items(
+    conn: &Connection,
+    blobs: &BlobStore,
+    cursor: Option<&ListCursor>,
+    limit: i64,
+    sort: EntrySort,
+    filters: &EntryFilters,
+    max_characters: usize,
+) -> Result<EntryPage, DatabaseError> {
+    let (mut conditions, mut params) = filters_to_sql(filters);
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    // Count the entries matching the filters, whatever the page
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM clipboard_entries e {}", where_clause),
+        params_from_iter(params.iter()),
+        |row| row.get(0),
+    )?;
+
+    let (sort_key, descending) = sort_to_sql(sort);
+    let direction = if descending { "DESC" } else { "ASC" };
+
+    // Start right after the last entry of the previous page
+    if let Some(cursor) = cursor {
+        let pinned = cursor.pinned as i64;
+        let key = match &cursor.key {
+            serde_json::Value::Number(n) => n
+                .as_i64()
+                .map(Value::Integer)
+                .unwrap_or_else(|| Value::Real(n.as_f64().unwrap_or_default())),
+            serde_json::Value::String(s) => Value::Text(s.clone()),
+            _ => Value::Null,
+        };
+        params.push(Value::Integer(pinned));
+        params.push(key);
+        params.push(Value::Integer(cursor.id));
+        let n = params.len();
+        conditions.push(format!(
+            "(e.pinned < ?{p} OR (e.pinned = ?{p} AND ({key}, e.id) {cmp} (?{k}, ?{i})))",
+            p = n - 2,
+            k = n - 1,
+            i = n,
+            key = sort_key,
+            cmp = if descending { "<" } else { ">" },
+        ));
+    }
+
+    // Only the beginning of the text is needed to build the preview
+    params.push(Value::Integer(max_characters as i64 + 1));
+    let preview_length = params.len();
+
+    // Fetch one more entry to know if there is a next page
+    let limit = limit.clamp(1, 500);
+    params.push(Value::Integer(limit + 1));
+    let limit_param = params.len();
+
+    let query = format!(
+        "SELECT e.id, e.type,
+            CASE WHEN e.type IN ('image', 'rich_text') THEN e.content ELSE substr(e.content, 1, ?{}) END,
+            e.added_at, e.pinned, e.forced_language, e.blob_hash, e.paste_count, e.last_used_at,
+            e.source_class, e.source_title, e.source_process, e.title, e.note, e.is_template,
+            e.sensitive, e.masked_content, e.expires_at, {},
+            {} AS sort_key
+        FROM clipboard_entries e
+        {}
+        ORDER BY e.pinned DESC, sort_key {dir}, e.id {dir}
+        LIMIT ?{}",
+        preview_length,
+        LINK_COLUMNS,
+        sort_key,
+        if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        },
+        limit_param,
+        dir = direction,
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let mut rows = stmt
+        .query_map(params_from_iter(params.iter()), |row| {
+            Ok((entry_from_row(row, blobs)?, row.get::<_, Value>(20)?))
+        })?
+        .collect::<rusqlite::Result<Vec<(ClipboardEntry, Value)>>>()?;
+
+    // Build the cursor from the last entry of the page
+    let mut next_cursor = None;
+    if rows.len() as i64 > limit {
+        rows.truncate(limit as usize);
+        if let Some((entry, key)) = rows.last() {
+            let key = match key {
+                Value::Integer(i) => serde_json::Value::from(*i),
+                Value::Real(f) => serde_json::Value::from(*f),
+                Value::Text(s) => serde_json::Value::from(s.clone()),
+                _ => serde_json::Value::Null,
+            };
+            let cursor = ListCursor {
+                pinned: entry.pinned,
+                key,
+                id: entry.id,
+            };
+            next_cursor = serde_json::to_string(&cursor).ok();
+        }
+    }
+
+    Ok(EntryPage {
+        entries: rows
+            .into_iter()
+            .map(|(entry, _)| to_preview(entry, max_characters))
+            .collect(),
+        next_cursor,
+        total,
+    })
+}
+
+// Function to record that an entry has been pushed back to the clipboard
+// Updates the usage counters and the frecency of the entry, and logs the use
+// With `move_to_top`, the entry also becomes the most recent one, as if it had just been copied
+pub fn record_item_usage(
+    conn: &mut Connection,
+    id: i64,
+    move_to_top: bool,
+) -> Result<(), DatabaseError> {
+    let tx = conn.transaction()?;
+
+    let frecency: f64 = tx
+        .query_row(
+            "SELECT frecency FROM clipboard_entries WHERE id = ?1",
+            [id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or(DatabaseError::NotFound(id))?;
+
+    tx.execute(
+        "UPDATE clipboard_entries
+        SET paste_count = paste_count + 1, last_used_at = CURRENT_TIMESTAMP, frecency = ?1,
+            added_at = CASE WHEN ?2 THEN CURRENT_TIMESTAMP ELSE added_at END
+        WHERE id = ?3",
+        (
+            add_frecency_event(frecency, frecency_now()),
+            move_to_top,
+            id,
+        ),
+    )?;
+    tx.execute("INSERT INTO entry_usage (entry_id) VALUES (?1)", [id])?;
+
+    tx.commit()?;
+    Ok(())
+}
+
+// Function to check that an entry exists
+fn ensure_item_exists(conn: &Connection, id: i64) -> Result<(), DatabaseError> {
+    conn.query_row(
+        "SELECT 1 FROM clipboard_entries WHERE id = ?1",
+        [id],
+        |_| Ok(()),
+    )
+    .optional()?
+    .ok_or(DatabaseError::NotFound(id))
+}
+
+// Function to check the name of a collection, returns it trimmed
+fn collection_name(name: &str) -> Result<String, DatabaseError> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_control) {
+        return Err(DatabaseError::InvalidInput(
+            "The name of a collection can't be empty".to_string(),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+// Function to normalize a tag (lowercase, without the leading `#`)
+// Tags can't contain spaces, so they can always be written in a search query (`tag:...`)
+fn normalize_tag(tag: &str) -> Result<String, DatabaseError> {
+    let tag = tag.trim().trim_start_matches('#').to_lowercase();
+    if tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || c.is_control()) {
+        return Err(DatabaseError::InvalidInput(
+            "A tag can't be empty or contain spaces".to_string(),
+        ));
+    }
+    Ok(tag)
+}
+
+// Function to get a collection by its ID
+fn get_collection(conn: &Connection, id: i64) -> Result<Collection, DatabaseError> {
+    conn.query_row(
+        "SELECT c.id, c.name, c.created_at, COUNT(ec.entry_id)
+        FROM collections c
+        LEFT JOIN entry_collections ec ON ec.collection_id = c.id
+        WHERE c.id = ?1
+        GROUP BY c.id",
+        [id],
+        collection_from_row,
+    )
+    .optional()?
+    .ok_or(DatabaseError::CollectionNotFound(id))
+}
+
+// Function to build a collection from a row (id, name, created_at, entry count)
+fn collection_from_row(row: &rusqlite::Row) -> rusqlite::Result<Collection> {
+    Ok(Collection {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_at: row.get(2)?,
+        entry_count: row.get(3)?,
+    })
+}
+
+// Function to make sure no other collection has the same name (names are case insensitive)
+fn ensure_collection_name_free(
+    conn: &Connection,
+    name: &str,
+    except: Option<i64>,
+) -> Result<(), DatabaseError> {
+    let existing: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM collections WHERE name = ?1 AND id != ?2",
+            (name, except.unwrap_or(-1)),
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    match existing {
+        Some(_) => Err(DatabaseError::InvalidInput(format!(
+            "A collection named \"{}\" already exists",
+            name
+        ))),
+        None => Ok(()),
+    }
+}
+
+// Function to create a collection
+pub fn create_collection(conn: &Connection, name: &str) -> Result<Collection, DatabaseError> {
+    let name = collection_name(name)?;
+    ensure_collection_name_free(conn, &name, None)?;
+
+    conn.execute("INSERT INTO collections (name) VALUES (?1)", [&name])?;
+    get_collection(conn, conn.last_insert_rowid())
+}
+
+// Function to rename a collection
+pub fn rename_collection(
+    conn: &Connection,
+    id: i64,
+    name: &str,
+) -> Result<Collection, DatabaseError> {
+    let name = collection_name(name)?;
+    ensure_collection_name_free(conn, &name, Some(id))?;
+
+    match conn.execute(
+        "UPDATE collections SET name = ?1 WHERE id = ?2",
+        (&name, id),
+    )? {
+        0 => Err(DatabaseError::CollectionNotFound(id)),
+        _ => get_collection(conn, id),
+    }
+}
+
+// Function to delete a collection, its entries are kept
+pub fn delete_collection(conn: &Connection, id: i64) -> Result<(), DatabaseError> {
+    match conn.execute("DELETE FROM collections WHERE id = ?1", [id])? {
+        0 => Err(DatabaseError::CollectionNotFound(id)),
+        _ => Ok(()),
+    }
+}
+
+// Function to get the ID of a collection from its name, the collection is created if needed
+pub fn collection_id_by_name(conn: &Connection, name: &str) -> Result<i64, DatabaseError> {
+    let name = collection_name(name)?;
+    conn.execute(
+        "INSERT OR IGNORE INTO collections (name) VALUES (?1)",
+        [&name],
+    )?;
+    Ok(conn.query_row(
+        "SELECT id FROM collections WHERE name = ?1",
+        [&name],
+        |row| row.get(0),
+    )?)
+}
+
+// Function to get all collections, sorted by name
+pub fn list_collections(conn: &Connection) -> Result<Vec<Collection>, DatabaseError> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.name, c.created_at, COUNT(ec.entry_id)
+        FROM collections c
+        LEFT JOIN entry_collections ec ON ec.collection_id = c.id
+        GROUP BY c.id
+        ORDER BY c.name",
+    )?;
+    let collections = stmt
+        .query_map([], collection_from_row)?
+        .collect::<rusqlite::Result<Vec<Collection>>>()?;
+
+    Ok(collections)
+}
+
+// Function to add an entry to a collection (nothing happens if it is already in the collection)
+pub fn add_item_to_collection(
+    conn: &Connection,
+    id: i64,
+    collection_id: i64,
+) -> Result<(), DatabaseError> {
+    ensure_item_exists(conn, id)?;
+    get_collection(conn, collection_id)?;
+
+    conn.execute(
+        "INSERT OR IGNORE INTO entry_collections (entry_id, collection_id) VALUES (?1, ?2)",
+        (id, collection_id),
+    )?;
+    Ok(())
+}
+
+// Function to remove an entry from a collection
+pub fn remove_item_from_collection(
+    conn: &Connection,
+    id: i64,
+    collection_id: i64,
+) -> Result<(), DatabaseError> {
+    ensure_item_exists(conn, id)?;
+    get_collection(conn, collection_id)?;
+
+    conn.execute(
+        "DELETE FROM entry_collections WHERE entry_id = ?1 AND collection_id = ?2",
+        (id, collection_id),
+    )?;
+    Ok(())
+}
+
+// Function to add a tag to an entry (nothing happens if the entry already has it)
+pub fn add_item_tag(conn: &Connection, id: i64, tag: &str) -> Result<(), DatabaseError> {
+    let tag = normalize_tag(tag)?;
+    ensure_item_exists(conn, id)?;
+
+    conn.execute(
+        "INSERT OR IGNORE INTO entry_tags (entry_id, tag) VALUES (?1, ?2)",
+        (id, &tag),
+    )?;
+    Ok(())
+}
+
+// Function to remove a tag from an entry
+pub fn remove_item_tag(conn: &Connection, id: i64, tag: &str) -> Result<(), DatabaseError> {
+    let tag = normalize_tag(tag)?;
+    ensure_item_exists(conn, id)?;
+
+    conn.execute(
+        "DELETE FROM entry_tags WHERE entry_id = ?1 AND tag = ?2",
+        (id, &tag),
+    )?;
+    Ok(())
 }
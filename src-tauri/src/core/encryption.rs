@@ -0,0 +1,467 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    XChaCha20Poly1305, XNonce,
+};
+use rand::{rngs::OsRng, RngCore};
+use rusqlite::Connection;
+
+use crate::{core::blob_store::BlobStore, structures::config::AppConfig};
+
+// Environment variable holding the passphrase when the key is derived from a passphrase
+pub const PASSPHRASE_VARIABLE: &str = "TACTICLIP_PASSPHRASE";
+
+// Header of the encrypted blobs, followed by the nonce and the ciphertext
+const BLOB_MAGIC: &[u8] = b"TCENC1";
+const NONCE_SIZE: usize = 24;
+
+// Header of a plaintext SQLite database
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+// Enum to represent where the key of the database comes from
+#[derive(PartialEq)]
+enum KeySource {
+    KeyFile,
+    Passphrase,
+}
+
+impl KeySource {
+    fn from_config(configuration: &AppConfig) -> KeySource {
+        match configuration.encryption_key_source.as_str() {
+            "passphrase" => KeySource::Passphrase,
+            _ => KeySource::KeyFile,
+        }
+    }
+}
+
+// Function to get the path of the key file (next to the database)
+pub fn get_key_path(db_path: &Path) -> PathBuf {
+    db_path.with_file_name("database.key")
+}
+
+// Function to get the path of the salt used to derive the key from the passphrase
+pub fn get_salt_path(db_path: &Path) -> PathBuf {
+    db_path.with_file_name("database.salt")
+}
+
+// Function to get the path of a file written during a key rotation, before the rotation is complete
+fn pending_path(path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.new", path.display()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim();
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+// Function to write a secret to a file that only the current user can read
+fn write_secret_file(path: &Path, data: &[u8]) -> Result<(), String> {
+    let temp_path = PathBuf::from(format!("{}.tmp", path.display()));
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(&temp_path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    file.write_all(data)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    fs::rename(&temp_path, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+// Function to read a key stored as hexadecimal in a file
+fn read_key_file(path: &Path) -> Option<[u8; 32]> {
+    let text = fs::read_to_string(path).ok()?;
+    from_hex(&text)?.try_into().ok()
+}
+
+// Function to derive a key from a passphrase and a salt (Argon2id)
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive the key from the passphrase: {}", e))?;
+    Ok(key)
+}
+
+// Function to get the passphrase from the environment
+fn passphrase() -> Result<String, String> {
+    std::env::var(PASSPHRASE_VARIABLE)
+        .ok()
+        .filter(|p| !p.is_empty())
+        .ok_or(format!(
+            "The database key is derived from a passphrase but {} is not set",
+            PASSPHRASE_VARIABLE
+        ))
+}
+
+// Function to get the candidate keys of the database, the pending key of an interrupted rotation coming last
+// When `create` is set and no key exists yet, a new one is generated
+fn candidate_keys(
+    configuration: &AppConfig,
+    db_path: &Path,
+    create: bool,
+) -> Result<Vec<[u8; 32]>, String> {
+    let mut keys: Vec<[u8; 32]> = Vec::new();
+
+    match KeySource::from_config(configuration) {
+        KeySource::KeyFile => {
+            let key_path = get_key_path(db_path);
+            keys.extend(read_key_file(&key_path));
+            keys.extend(read_key_file(&pending_path(&key_path)));
+
+            if keys.is_empty() && create {
+                let key = random_bytes::<32>();
+                write_secret_file(&key_path, to_hex(&key).as_bytes())?;
+                println!("Created a new database key at {:?}", key_path);
+                keys.push(key);
+            }
+        }
+        KeySource::Passphrase => {
+            let passphrase = passphrase()?;
+            let salt_path = get_salt_path(db_path);
+            for path in [salt_path.clone(), pending_path(&salt_path)] {
+                if let Ok(salt) = fs::read(&path) {
+                    keys.push(derive_key(&passphrase, &salt)?);
+                }
+            }
+
+            if keys.is_empty() && create {
+                let salt = random_bytes::<16>();
+                write_secret_file(&salt_path, &salt)?;
+                keys.push(derive_key(&passphrase, &salt)?);
+            }
+        }
+    }
+
+    Ok(keys)
+}
+
+// Function to check if a database file is a plaintext SQLite database
+fn is_plaintext_database(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map(|_| header == SQLITE_HEADER)
+        .unwrap_or(false)
+}
+
+// Function to unlock a connection with a key, returns false if the key is wrong
+fn apply_key(conn: &Connection, key: &[u8; 32]) -> bool {
+    conn.execute_batch(&format!("PRAGMA key = \"x'{}'\";", to_hex(key)))
+        .is_ok()
+        && conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .is_ok()
+}
+
+// Function to copy a database into a new file, encrypted with `key` (or in plaintext without key)
+pub fn export_database(
+    source: &Connection,
+    dest: &Path,
+    key: Option<&[u8; 32]>,
+) -> rusqlite::Result<()> {
+    let key = key.map(|k| format!("x'{}'", to_hex(k))).unwrap_or_default();
+
+    source.execute(
+        "ATTACH DATABASE ?1 AS export KEY ?2",
+        (dest.to_string_lossy().to_string(), key),
+    )?;
+    source.query_row("SELECT sqlcipher_export('export')", [], |_| Ok(()))?;
+
+    // The schema version is not part of the export
+    let version: i64 = source.query_row("PRAGMA main.user_version", [], |row| row.get(0))?;
+    source.execute_batch(&format!("PRAGMA export.user_version = {}", version))?;
+    source.execute_batch("DETACH DATABASE export")
+}
+
+// Function to rewrite a database file with another key, the original is only replaced once the copy is complete
+fn convert_database(
+    path: &Path,
+    from: Option<&[u8; 32]>,
+    to: Option<&[u8; 32]>,
+) -> Result<(), String> {
+    let temp_path = PathBuf::from(format!("{}.converting", path.display()));
+    let _ = fs::remove_file(&temp_path);
+
+    {
+        let source = Connection::open(path).map_err(|e| e.to_string())?;
+        if let Some(key) = from {
+            if !apply_key(&source, key) {
+                return Err("Wrong database key".to_string());
+            }
+        }
+        export_database(&source, &temp_path, to)
+            .map_err(|e| format!("Failed to convert the database: {}", e))?;
+    }
+
+    fs::rename(&temp_path, path).map_err(|e| format!("Failed to replace the database: {}", e))
+}
+
+// Function to open the database, encrypting (or decrypting) it first if it doesn't match the configuration
+// Returns the connection along with the key it was unlocked with, to open more connections later
+pub fn open_encrypted_database(
+    path: &Path,
+    configuration: &AppConfig,
+) -> Result<(Connection, Option<[u8; 32]>), String> {
+    let exists = path.exists() && fs::metadata(path).map(|m| m.len() > 0).unwrap_or(false);
+
+    if configuration.encryption_enabled {
+        let keys = candidate_keys(configuration, path, true)?;
+
+        // One-time migration of a plaintext database
+        if exists && is_plaintext_database(path) {
+            println!("Encrypting the database, this may take a while...");
+            convert_database(path, None, Some(&keys[0]))?;
+        }
+
+        for (index, key) in keys.iter().enumerate() {
+            let conn = Connection::open(path).map_err(|e| e.to_string())?;
+            if apply_key(&conn, key) {
+                // The database was rekeyed but the rotation was interrupted before the new key replaced the old one
+                if index > 0 {
+                    finish_rotation(configuration, path);
+                }
+                return Ok((conn, Some(*key)));
+            }
+        }
+
+        Err("Unable to decrypt the database with the configured key".to_string())
+    } else if exists && !is_plaintext_database(path) {
+        // Encryption was disabled, decrypt the database with its previous key
+        let keys = candidate_keys(configuration, path, false)?;
+        let key = keys
+            .iter()
+            .find(|key| {
+                Connection::open(path)
+                    .map(|conn| apply_key(&conn, key))
+                    .unwrap_or(false)
+            })
+            .ok_or("The database is encrypted but its key is unavailable")?;
+
+        println!("Decrypting the database, this may take a while...");
+        convert_database(path, Some(key), None)?;
+        Ok((open_with_key(path, None)?, None))
+    } else {
+        Ok((open_with_key(path, None)?, None))
+    }
+}
+
+// Function to open another connection to a database that is already in the right state
+pub fn open_with_key(path: &Path, key: Option<&[u8; 32]>) -> Result<Connection, String> {
+    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+    match key {
+        Some(key) if !apply_key(&conn, key) => Err("Wrong database key".to_string()),
+        _ => Ok(conn),
+    }
+}
+
+// Function to open a copy of the database (a backup) with the key files kept next to it, or with `fallback`
+// Returns the connection along with the key it was unlocked with
+pub fn open_database_copy(
+    path: &Path,
+    configuration: &AppConfig,
+    fallback: Option<&[u8; 32]>,
+) -> Result<(Connection, Option<[u8; 32]>), String> {
+    if is_plaintext_database(path) {
+        return Ok((open_with_key(path, None)?, None));
+    }
+
+    // Without the passphrase only the fallback key can be tried
+    let mut keys = candidate_keys(configuration, path, false).unwrap_or_default();
+    keys.extend(fallback.copied());
+
+    for key in keys {
+        let conn = Connection::open(path).map_err(|e| e.to_string())?;
+        if apply_key(&conn, &key) {
+            return Ok((conn, Some(key)));
+        }
+    }
+
+    Err("Unable to decrypt the copy of the database with its key".to_string())
+}
+
+// Function to move the files of a completed key rotation in place
+fn finish_rotation(configuration: &AppConfig, db_path: &Path) {
+    let path = match KeySource::from_config(configuration) {
+        KeySource::KeyFile => get_key_path(db_path),
+        KeySource::Passphrase => get_salt_path(db_path),
+    };
+    let _ = fs::rename(pending_path(&path), &path);
+}
+
+// Function to change the key of the database, returns the new key
+// With a key file a new random key is generated, with a passphrase the new passphrase is required
+pub fn rotate_key(
+    conn: &Connection,
+    configuration: &AppConfig,
+    new_passphrase: Option<&str>,
+) -> Result<[u8; 32], String> {
+    if !configuration.encryption_enabled {
+        return Err("Encryption at rest is disabled".to_string());
+    }
+
+    // The key files live next to the database, an in-memory database has none
+    let db_path = conn
+        .path()
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .ok_or("The database is not stored in a file")?;
+
+    // Write the new key next to the current one first, so an interruption never loses the key
+    let source = KeySource::from_config(configuration);
+    let new_key = match source {
+        KeySource::KeyFile => {
+            let key = random_bytes::<32>();
+            write_secret_file(
+                &pending_path(&get_key_path(&db_path)),
+                to_hex(&key).as_bytes(),
+            )?;
+            key
+        }
+        KeySource::Passphrase => {
+            let passphrase = new_passphrase
+                .filter(|p| !p.is_empty())
+                .ok_or("A new passphrase is required")?;
+            let salt = random_bytes::<16>();
+            write_secret_file(&pending_path(&get_salt_path(&db_path)), &salt)?;
+            derive_key(passphrase, &salt)?
+        }
+    };
+
+    conn.execute_batch(&format!("PRAGMA rekey = \"x'{}'\";", to_hex(&new_key)))
+        .map_err(|e| format!("Failed to change the database key: {}", e))?;
+
+    finish_rotation(configuration, &db_path);
+
+    if source == KeySource::Passphrase {
+        println!(
+            "Database key rotated, set {} to the new passphrase",
+            PASSPHRASE_VARIABLE
+        );
+    }
+
+    Ok(new_key)
+}
+
+// Function to load the key of the blobs from the database, creating it when encryption is enabled
+// Then encrypt (or decrypt) the existing blobs to match the setting
+pub fn init_blob_encryption(
+    conn: &Connection,
+    blobs: &BlobStore,
+    enabled: bool,
+) -> Result<(), String> {
+    let mut key: Option<[u8; 32]> = conn
+        .query_row(
+            "SELECT key FROM encryption_keys WHERE name = 'blobs'",
+            [],
+            |row| row.get::<_, Vec<u8>>(0),
+        )
+        .ok()
+        .and_then(|k| k.try_into().ok());
+
+    if key.is_none() && enabled {
+        let new_key = random_bytes::<32>();
+        conn.execute(
+            "INSERT INTO encryption_keys (name, key) VALUES ('blobs', ?1)",
+            [&new_key[..]],
+        )
+        .map_err(|e| format!("Failed to store the blob key: {}", e))?;
+        key = Some(new_key);
+    }
+
+    blobs.set_key(key, enabled);
+
+    // Convert the blobs written under the other setting
+    let mut failures = 0;
+    if let Ok(dir) = fs::read_dir(blobs.dir()) {
+        for file in dir.filter_map(Result::ok) {
+            let path = file.path();
+            let Ok(data) = fs::read(&path) else {
+                continue;
+            };
+
+            let converted = match (key, data.starts_with(BLOB_MAGIC)) {
+                (Some(key), false) if enabled => encrypt_blob(&data, &key),
+                (key, true) if !enabled => decrypt_blob(&data, key.as_ref()),
+                _ => continue,
+            };
+
+            match converted.and_then(|bytes| write_secret_file(&path, &bytes)) {
+                Ok(()) => {}
+                Err(e) => {
+                    eprintln!("Failed to convert blob {:?}: {}", path, e);
+                    failures += 1;
+                }
+            }
+        }
+    }
+
+    // Once every blob is decrypted, the key is no longer needed
+    if !enabled && key.is_some() && failures == 0 {
+        let _ = conn.execute("DELETE FROM encryption_keys WHERE name = 'blobs'", []);
+        blobs.set_key(None, false);
+    }
+
+    Ok(())
+}
+
+// Function to encrypt a blob with the key of the blob store
+pub fn encrypt_blob(data: &[u8], key: &[u8; 32]) -> Result<Vec<u8>, String> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = random_bytes::<NONCE_SIZE>();
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), data)
+        .map_err(|_| "Failed to encrypt blob".to_string())?;
+
+    Ok([BLOB_MAGIC, &nonce, &ciphertext].concat())
+}
+
+// Function to decrypt a blob (plaintext blobs are returned unchanged)
+pub fn decrypt_blob(data: &[u8], key: Option<&[u8; 32]>) -> Result<Vec<u8>, String> {
+    let Some(payload) = data.strip_prefix(BLOB_MAGIC) else {
+        return Ok(data.to_vec());
+    };
+
+    if payload.len() < NONCE_SIZE {
+        return Err("Truncated encrypted blob".to_string());
+    }
+
+    let key = key.ok_or("The blob key is not loaded")?;
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let (nonce, ciphertext) = payload.split_at(NONCE_SIZE);
+    cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt blob".to_string())
+}
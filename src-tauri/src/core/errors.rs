@@ -0,0 +1,109 @@
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use crate::core::templates::TemplateError;
+
+// Errors raised while accessing the history
+#[derive(Debug, thiserror::Error)]
+pub enum DatabaseError {
+    #[error("The storage service is unavailable")]
+    Unavailable,
+
+    #[error("Clipboard entry {0} not found")]
+    NotFound(i64),
+
+    #[error("Collection {0} not found")]
+    CollectionNotFound(i64),
+
+    #[error("{0}")]
+    InvalidInput(String),
+
+    #[error("Database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("Migration error: {0}")]
+    Migration(String),
+
+    #[error("File system error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Image store error: {0}")]
+    Blob(String),
+
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    #[error("Archive error: {0}")]
+    Archive(String),
+
+    #[error("Compression error: {0}")]
+    Compression(String),
+
+    #[error("Backup {0} not found")]
+    BackupNotFound(String),
+}
+
+// Errors returned by the Tauri commands, serialized as `{ kind, message }` for the frontend
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
+
+    #[error("Clipboard error: {0}")]
+    Clipboard(String),
+
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    #[error("Window error: {0}")]
+    Window(#[from] tauri::Error),
+
+    #[error("Invalid template: {0}")]
+    Template(#[from] TemplateError),
+
+    #[error("The template needs a value for {}", .0.join(", "))]
+    InputRequired(Vec<String>), // Names of the inputs to ask the user
+}
+
+impl AppError {
+    // Identifier of the error, stable enough for the frontend to react to a specific failure
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::Database(DatabaseError::Unavailable) => "unavailable",
+            AppError::Database(DatabaseError::NotFound(_)) => "not_found",
+            AppError::Database(DatabaseError::CollectionNotFound(_)) => "not_found",
+            AppError::Database(DatabaseError::InvalidInput(_)) => "invalid_input",
+            AppError::Database(DatabaseError::Sqlite(_)) => "database",
+            AppError::Database(DatabaseError::Migration(_)) => "migration",
+            AppError::Database(DatabaseError::Io(_)) => "io",
+            AppError::Database(DatabaseError::Blob(_)) => "blob",
+            AppError::Database(DatabaseError::Encryption(_)) => "encryption",
+            AppError::Database(DatabaseError::Archive(_)) => "archive",
+            AppError::Database(DatabaseError::Compression(_)) => "compression",
+            AppError::Database(DatabaseError::BackupNotFound(_)) => "not_found",
+            AppError::Clipboard(_) => "clipboard",
+            AppError::Config(_) => "config",
+            AppError::Window(_) => "window",
+            AppError::Template(_) => "template",
+            AppError::InputRequired(_) => "input_required",
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Template errors carry what the frontend needs to point at the error or to ask for the inputs
+        let fields = match self {
+            AppError::Template(_) | AppError::InputRequired(_) => 3,
+            _ => 2,
+        };
+        let mut state = serializer.serialize_struct("AppError", fields)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        match self {
+            AppError::Template(e) => state.serialize_field("position", &e.position)?,
+            AppError::InputRequired(inputs) => state.serialize_field("inputs", inputs)?,
+            _ => {}
+        }
+        state.end()
+    }
+}
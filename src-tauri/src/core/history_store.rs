@@ -0,0 +1,891 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use rusqlite::Connection;
+
+use crate::{
+    core::{
+        archive::{export_archive, import_archive},
+        backups::{
+            create_backup, list_backups, restore_backup, rotate_backups, time_since_last_backup,
+        },
+        blob_store::BlobStore,
+        compression::CompressedText,
+        database_api::{
+            add_item_tag, add_item_to_collection, create_collection, delete_collection,
+            delete_item_by_id, empty_trash, get_all_ids, get_item_by_id, get_last_item_copied,
+            get_truncated_item_by_id, insert_clipboard_entry, insert_compressed_clipboard_entry,
+            insert_image_entry, list_collections, list_items, pin_item_by_id, prune_items,
+            purge_expired_items, purge_trash, record_item_usage, remove_item_from_collection,
+            remove_item_tag, rename_collection, restore_item_by_id, search_items,
+            set_forced_language, set_item_expiry, set_item_note, set_item_template, set_item_title,
+            trash_all_items, trash_item_by_id, undo_last_operation, unpin_all_items,
+            unpin_item_by_id,
+        },
+        encryption::{init_blob_encryption, open_encrypted_database, rotate_key},
+        errors::DatabaseError,
+        migrations::run_migrations,
+        storage::{Storage, BUSY_TIMEOUT},
+    },
+    structures::{
+        archive::{ImportConflict, ImportReport},
+        backup::Backup,
+        clipboard_entry::ClipboardEntry,
+        collection::Collection,
+        config::AppConfig,
+        entry_filters::EntryFilters,
+        entry_marks::EntryMarks,
+        entry_page::{EntryPage, EntrySort, ListCursor},
+        operation::Operation,
+        search_result::SearchResult,
+        source_app::SourceApp,
+    },
+};
+
+// History shared by the watcher, the tasks and the commands (registered as Tauri state)
+pub type SharedHistoryStore = Arc<dyn HistoryStore>;
+
+// Operations on the clipboard history, independent of where the history is kept
+// The watcher and the commands only go through this trait, so the history engine can be embedded in
+// other tools or exercised without touching the history of the user
+#[async_trait]
+pub trait HistoryStore: Send + Sync {
+    // Function to insert a text entry (text, url, email, color...), returns its ID and whether it already existed
+    // `source` is the application the content was copied from, when known, and `marks` are stored along with the
+    // entry (they replace those of the existing entry on deduplication)
+    async fn insert_text(
+        &self,
+        entry_type: &str,
+        content: &str,
+        source: Option<&SourceApp>,
+        marks: &EntryMarks,
+        deduplicate: bool,
+    ) -> Result<(i64, bool), DatabaseError>;
+
+    // Function to insert a text compressed by `compress_text`, returns its ID and whether it already existed
+    async fn insert_compressed_text(
+        &self,
+        entry_type: &str,
+        text: CompressedText,
+        source: Option<&SourceApp>,
+        marks: &EntryMarks,
+        deduplicate: bool,
+    ) -> Result<(i64, bool), DatabaseError>;
+
+    // Function to insert a base64 encoded image, returns its ID and whether it already existed
+    async fn insert_image(
+        &self,
+        base64_image: &str,
+        source: Option<&SourceApp>,
+        marks: &EntryMarks,
+        deduplicate: bool,
+    ) -> Result<(i64, bool), DatabaseError>;
+
+    // Function to get the full content of an entry
+    async fn get(&self, id: i64) -> Result<ClipboardEntry, DatabaseError>;
+
+    // Function to get the preview of an entry (plain text only, truncated to `max_characters`)
+    async fn preview(
+        &self,
+        id: i64,
+        max_characters: usize,
+    ) -> Result<ClipboardEntry, DatabaseError>;
+
+    // Function to get the most recent entry of a type
+    async fn last_of_type(&self, entry_type: &str)
+        -> Result<Option<ClipboardEntry>, DatabaseError>;
+
+    // Function to get the IDs of all entries, most recent first
+    async fn ids(&self) -> Result<Vec<i64>, DatabaseError>;
+
+    // Function to get a page of previews, pinned entries first
+    async fn list(
+        &self,
+        cursor: Option<ListCursor>,
+        limit: i64,
+        sort: EntrySort,
+        filters: EntryFilters,
+        max_characters: usize,
+    ) -> Result<EntryPage, DatabaseError>;
+
+    // Function to pin or unpin an entry
+    async fn set_pinned(&self, id: i64, pinned: bool) -> Result<(), DatabaseError>;
+
+    // Function to unpin all entries, returns the number of unpinned entries
+    async fn unpin_all(&self) -> Result<i64, DatabaseError>;
+
+    // Function to force the language used to highlight an entry
+    async fn set_forced_language(&self, id: i64, language: &str) -> Result<(), DatabaseError>;
+
+    // Function to set the title of an entry, None or a blank title removes it
+    async fn set_title(&self, id: i64, title: Option<&str>) -> Result<(), DatabaseError>;
+
+    // Function to set the note of an entry, None or a blank note removes it
+    async fn set_note(&self, id: i64, note: Option<&str>) -> Result<(), DatabaseError>;
+
+    // Function to mark an entry as a template or as a regular entry
+    async fn set_template(&self, id: i64, is_template: bool) -> Result<(), DatabaseError>;
+
+    // Function to give an entry a lifetime in minutes (None keeps it forever), returns its new expiry date
+    async fn set_expiry(
+        &self,
+        id: i64,
+        minutes: Option<i64>,
+    ) -> Result<Option<String>, DatabaseError>;
+
+    // Function to delete the entries whose expiry date has passed, returns their IDs
+    async fn purge_expired(&self) -> Result<Vec<i64>, DatabaseError>;
+
+    // Function to record that an entry has been pushed back to the clipboard, `move_to_top` makes it the most recent one
+    async fn record_usage(&self, id: i64, move_to_top: bool) -> Result<(), DatabaseError>;
+
+    // Function to delete an entry for good, bypassing the trash
+    async fn delete(&self, id: i64) -> Result<(), DatabaseError>;
+
+    // Function to move an entry to the trash
+    async fn trash(&self, id: i64) -> Result<(), DatabaseError>;
+
+    // Function to move all entries to the trash, returns the number of trashed entries
+    async fn trash_all(&self) -> Result<i64, DatabaseError>;
+
+    // Function to restore an entry from the trash
+    async fn restore(&self, id: i64) -> Result<(), DatabaseError>;
+
+    // Function to delete the entries of the trash for good, returns the number of deleted entries
+    async fn empty_trash(&self) -> Result<i64, DatabaseError>;
+
+    // Function to delete the entries trashed more than `days` days ago, returns the number of deleted entries
+    async fn purge_trash(&self, days: i64) -> Result<i64, DatabaseError>;
+
+    // Function to reverse the most recent destructive operation (None if there is nothing to undo)
+    async fn undo_last_operation(&self) -> Result<Option<Operation>, DatabaseError>;
+
+    // Function to search the entries, returns ranked IDs with highlighted snippets
+    async fn search(
+        &self,
+        text: &str,
+        filters: EntryFilters,
+        preview_length: usize,
+    ) -> Result<Vec<SearchResult>, DatabaseError>;
+
+    // Function to apply the retention policy, returns the IDs of the deleted entries
+    async fn prune(&self, configuration: &AppConfig) -> Result<Vec<i64>, DatabaseError>;
+
+    // Function to export the entries matching `filters` to an archive, returns the number of exported entries
+    async fn export(&self, path: &Path, filters: EntryFilters) -> Result<usize, DatabaseError>;
+
+    // Function to import an archive, duplicates are handled according to `conflict`
+    async fn import(
+        &self,
+        path: &Path,
+        conflict: ImportConflict,
+    ) -> Result<ImportReport, DatabaseError>;
+
+    // Function to create a collection (names are unique, case insensitive)
+    async fn create_collection(&self, name: &str) -> Result<Collection, DatabaseError>;
+
+    // Function to rename a collection
+    async fn rename_collection(&self, id: i64, name: &str) -> Result<Collection, DatabaseError>;
+
+    // Function to delete a collection, its entries are kept
+    async fn delete_collection(&self, id: i64) -> Result<(), DatabaseError>;
+
+    // Function to get all collections, sorted by name
+    async fn list_collections(&self) -> Result<Vec<Collection>, DatabaseError>;
+
+    // Function to add an entry to a collection
+    async fn add_to_collection(&self, id: i64, collection_id: i64) -> Result<(), DatabaseError>;
+
+    // Function to remove an entry from a collection
+    async fn remove_from_collection(
+        &self,
+        id: i64,
+        collection_id: i64,
+    ) -> Result<(), DatabaseError>;
+
+    // Function to add a tag to an entry
+    async fn add_tag(&self, id: i64, tag: &str) -> Result<(), DatabaseError>;
+
+    // Function to remove a tag from an entry
+    async fn remove_tag(&self, id: i64, tag: &str) -> Result<(), DatabaseError>;
+}
+
+// History kept in a SQLite database, served by the storage threads
+pub struct SqliteHistoryStore {
+    storage: Storage,
+    blobs: BlobStore,
+    db_path: Option<PathBuf>,      // None for a history kept in memory
+    key: RwLock<Option<[u8; 32]>>, // Current key of the database, used to open the backups
+    temporary: bool,               // The blob store is removed along with the history
+}
+
+impl SqliteHistoryStore {
+    // Function to open, migrate and prepare a history stored in a database file
+    // The images are stored in a `blobs` directory next to the database
+    pub fn open(
+        db_path: &Path,
+        configuration: &AppConfig,
+    ) -> Result<SqliteHistoryStore, DatabaseError> {
+        println!("DB path: {:?}", db_path);
+
+        // Create all directories leading to the database file
+        if let Some(parent) = db_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Open the database connection, encrypted at rest unless disabled in the configuration
+        let (mut conn, key) =
+            open_encrypted_database(db_path, configuration).map_err(DatabaseError::Encryption)?;
+
+        // Write-ahead logging lets the readers work while an entry is being written
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")?;
+
+        let blobs = BlobStore::next_to(db_path);
+        prepare(&mut conn, &blobs, configuration.encryption_enabled)?;
+
+        Ok(SqliteHistoryStore {
+            storage: Storage::start(conn, Some(db_path), key)?,
+            blobs,
+            db_path: Some(db_path.to_path_buf()),
+            key: RwLock::new(key),
+            temporary: false,
+        })
+    }
+
+    // Function to create an empty history kept in memory, dropped along with the store
+    // Only the images go to a temporary directory, removed on drop
+    // Public entry point for the tools embedding the history engine and for the tests, the app itself never calls it
+    #[allow(dead_code)]
+    pub fn in_memory() -> Result<SqliteHistoryStore, DatabaseError> {
+        let mut conn = Connection::open_in_memory()?;
+        let blobs = BlobStore::new(temporary_blob_dir());
+        prepare(&mut conn, &blobs, false)?;
+
+        Ok(SqliteHistoryStore {
+            storage: Storage::start(conn, None, None)?,
+            blobs,
+            db_path: None,
+            key: RwLock::new(None),
+            temporary: true,
+        })
+    }
+
+    // Function to change the key of the encrypted database (a new passphrase is required in passphrase mode)
+    pub async fn rotate_key(
+        &self,
+        configuration: AppConfig,
+        passphrase: Option<String>,
+    ) -> Result<(), DatabaseError> {
+        let key = self
+            .storage
+            .write(move |conn| {
+                rotate_key(conn, &configuration, passphrase.as_deref())
+                    .map_err(DatabaseError::Encryption)
+            })
+            .await?;
+
+        // The read-only connections are still unlocked with the previous key
+        *self.key.write().unwrap_or_else(PoisonError::into_inner) = Some(key);
+        self.storage.reopen_readers(Some(key));
+
+        Ok(())
+    }
+
+    // Function to get the path of the database file, backups need one
+    fn file_path(&self) -> Result<PathBuf, DatabaseError> {
+        self.db_path.clone().ok_or(DatabaseError::InvalidInput(
+            "The history is not stored in a file".to_string(),
+        ))
+    }
+
+    // Function to get the backups of the database, the most recent first
+    pub fn list_backups(&self) -> Vec<Backup> {
+        self.db_path
+            .as_deref()
+            .map(list_backups)
+            .unwrap_or_default()
+    }
+
+    // Function to back up the database if the last backup is older than the configured interval
+    // The old backups are rotated afterwards, returns the new backup (None if none was due)
+    pub async fn backup_if_due(
+        &self,
+        configuration: &AppConfig,
+    ) -> Result<Option<Backup>, DatabaseError> {
+        let db_path = self.file_path()?;
+        let interval = Duration::from_secs(configuration.backup_interval_hours.max(1) * 3600);
+        if time_since_last_backup(&db_path).is_some_and(|elapsed| elapsed < interval) {
+            return Ok(None);
+        }
+
+        let (blobs, key) = (self.blobs.clone(), self.current_key());
+        let (keep_daily, keep_weekly) = (
+            configuration.backup_keep_daily,
+            configuration.backup_keep_weekly,
+        );
+        // Runs on the writer, so the rotation can't delete a backup a restore is reading
+        self.storage
+            .write(move |conn| {
+                let backup = create_backup(conn, &blobs, &db_path, key.as_ref())?;
+                rotate_backups(conn, &db_path, keep_daily, keep_weekly)?;
+                Ok(Some(backup))
+            })
+            .await
+    }
+
+    // Function to replace the history with a backup, the current history is backed up first
+    pub async fn restore_backup(
+        &self,
+        configuration: AppConfig,
+        id: String,
+    ) -> Result<(), DatabaseError> {
+        let db_path = self.file_path()?;
+        let (blobs, key) = (self.blobs.clone(), self.current_key());
+        self.storage
+            .write(move |conn| {
+                restore_backup(conn, &blobs, &db_path, &configuration, key.as_ref(), &id)?;
+
+                // The backup may predate some migrations or hold other images
+                prepare(conn, &blobs, configuration.encryption_enabled)
+            })
+            .await
+    }
+
+    // Function to get the key the database is currently encrypted with
+    fn current_key(&self) -> Option<[u8; 32]> {
+        *self.key.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for SqliteHistoryStore {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_dir_all(self.blobs.dir());
+        }
+    }
+}
+
+// Function to bring a freshly opened database up to date before serving it
+fn prepare(
+    conn: &mut Connection,
+    blobs: &BlobStore,
+    encryption_enabled: bool,
+) -> Result<(), DatabaseError> {
+    // Bring the schema up to date, never start on a half-upgraded database
+    run_migrations(conn, blobs).map_err(|e| DatabaseError::Migration(e.to_string()))?;
+
+    // Load the key of the images and make sure they match the encryption setting
+    init_blob_encryption(conn, blobs, encryption_enabled).map_err(DatabaseError::Encryption)?;
+
+    // Remove the images left behind by entries deleted while the history was closed
+    let orphans = blobs.collect_orphans(conn);
+    if orphans > 0 {
+        println!("Deleted {} orphaned images", orphans);
+    }
+
+    Ok(())
+}
+
+// Function to get a directory for the images of an in-memory history, unique to the store
+fn temporary_blob_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("tacticlip-blobs-{:016x}", rand::random::<u64>()))
+}
+
+#[async_trait]
+impl HistoryStore for SqliteHistoryStore {
+    async fn insert_text(
+        &self,
+        entry_type: &str,
+        content: &str,
+        source: Option<&SourceApp>,
+        marks: &EntryMarks,
+        deduplicate: bool,
+    ) -> Result<(i64, bool), DatabaseError> {
+        let (entry_type, content) = (entry_type.to_string(), content.to_string());
+        let (source, marks) = (source.cloned(), marks.clone());
+        self.storage
+            .write(move |conn| {
+                insert_clipboard_entry(
+                    conn,
+                    &entry_type,
+                    &content,
+                    0,
+                    source.as_ref(),
+                    &marks,
+                    deduplicate,
+                )
+            })
+            .await
+    }
+
+    async fn insert_compressed_text(
+        &self,
+        entry_type: &str,
+        text: CompressedText,
+        source: Option<&SourceApp>,
+        marks: &EntryMarks,
+        deduplicate: bool,
+    ) -> Result<(i64, bool), DatabaseError> {
+        let entry_type = entry_type.to_string();
+        let (source, marks) = (source.cloned(), marks.clone());
+        self.storage
+            .write(move |conn| {
+                insert_compressed_clipboard_entry(
+                    conn,
+                    &entry_type,
+                    &text,
+                    0,
+                    source.as_ref(),
+                    &marks,
+                    deduplicate,
+                )
+            })
+            .await
+    }
+
+    async fn insert_image(
+        &self,
+        base64_image: &str,
+        source: Option<&SourceApp>,
+        marks: &EntryMarks,
+        deduplicate: bool,
+    ) -> Result<(i64, bool), DatabaseError> {
+        let (blobs, base64_image) = (self.blobs.clone(), base64_image.to_string());
+        let (source, marks) = (source.cloned(), marks.clone());
+        self.storage
+            .write(move |conn| {
+                insert_image_entry(
+                    conn,
+                    &blobs,
+                    &base64_image,
+                    0,
+                    source.as_ref(),
+                    &marks,
+                    deduplicate,
+                )
+            })
+            .await
+    }
+
+    async fn get(&self, id: i64) -> Result<ClipboardEntry, DatabaseError> {
+        let blobs = self.blobs.clone();
+        self.storage
+            .read(move |conn| get_item_by_id(conn, &blobs, id))
+            .await
+    }
+
+    async fn preview(
+        &self,
+        id: i64,
+        max_characters: usize,
+    ) -> Result<ClipboardEntry, DatabaseError> {
+        let blobs = self.blobs.clone();
+        self.storage
+            .read(move |conn| get_truncated_item_by_id(conn, &blobs, id, max_characters))
+            .await
+    }
+
+    async fn last_of_type(
+        &self,
+        entry_type: &str,
+    ) -> Result<Option<ClipboardEntry>, DatabaseError> {
+        let (blobs, entry_type) = (self.blobs.clone(), entry_type.to_string());
+        self.storage
+            .read(move |conn| get_last_item_copied(conn, &blobs, &entry_type))
+            .await
+    }
+
+    async fn ids(&self) -> Result<Vec<i64>, DatabaseError> {
+        self.storage.read(get_all_ids).await
+    }
+
+    async fn list(
+        &self,
+        cursor: Option<ListCursor>,
+        limit: i64,
+        sort: EntrySort,
+        filters: EntryFilters,
+        max_characters: usize,
+    ) -> Result<EntryPage, DatabaseError> {
+        let blobs = self.blobs.clone();
+        self.storage
+            .read(move |conn| {
+                list_items(
+                    conn,
+                    &blobs,
+                    cursor.as_ref(),
+                    limit,
+                    sort,
+                    &filters,
+                    max_characters,
+                )
+            })
+            .await
+    }
+
+    async fn set_pinned(&self, id: i64, pinned: bool) -> Result<(), DatabaseError> {
+        self.storage
+            .write(move |conn| match pinned {
+                true => pin_item_by_id(conn, id),
+                false => unpin_item_by_id(conn, id),
+            })
+            .await
+    }
+
+    async fn unpin_all(&self) -> Result<i64, DatabaseError> {
+        self.storage.write(|conn| unpin_all_items(conn)).await
+    }
+
+    async fn set_forced_language(&self, id: i64, language: &str) -> Result<(), DatabaseError> {
+        let language = language.to_string();
+        self.storage
+            .write(move |conn| set_forced_language(conn, id, &language))
+            .await
+    }
+
+    async fn set_title(&self, id: i64, title: Option<&str>) -> Result<(), DatabaseError> {
+        let title = title.map(str::to_string);
+        self.storage
+            .write(move |conn| set_item_title(conn, id, title.as_deref()))
+            .await
+    }
+
+    async fn set_note(&self, id: i64, note: Option<&str>) -> Result<(), DatabaseError> {
+        let note = note.map(str::to_string);
+        self.storage
+            .write(move |conn| set_item_note(conn, id, note.as_deref()))
+            .await
+    }
+
+    async fn set_template(&self, id: i64, is_template: bool) -> Result<(), DatabaseError> {
+        self.storage
+            .write(move |conn| set_item_template(conn, id, is_template))
+            .await
+    }
+
+    async fn set_expiry(
+        &self,
+        id: i64,
+        minutes: Option<i64>,
+    ) -> Result<Option<String>, DatabaseError> {
+        self.storage
+            .write(move |conn| set_item_expiry(conn, id, minutes))
+            .await
+    }
+
+    async fn purge_expired(&self) -> Result<Vec<i64>, DatabaseError> {
+        let blobs = self.blobs.clone();
+        self.storage
+            .write(move |conn| purge_expired_items(conn, &blobs))
+            .await
+    }
+
+    async fn record_usage(&self, id: i64, move_to_top: bool) -> Result<(), DatabaseError> {
+        self.storage
+            .write(move |conn| record_item_usage(conn, id, move_to_top))
+            .await
+    }
+
+    async fn delete(&self, id: i64) -> Result<(), DatabaseError> {
+        let blobs = self.blobs.clone();
+        self.storage
+            .write(move |conn| delete_item_by_id(conn, &blobs, id))
+            .await
+    }
+
+    async fn trash(&self, id: i64) -> Result<(), DatabaseError> {
+        self.storage
+            .write(move |conn| trash_item_by_id(conn, id))
+            .await
+    }
+
+    async fn trash_all(&self) -> Result<i64, DatabaseError> {
+        self.storage.write(|conn| trash_all_items(conn)).await
+    }
+
+    async fn restore(&self, id: i64) -> Result<(), DatabaseError> {
+        self.storage
+            .write(move |conn| restore_item_by_id(conn, id))
+            .await
+    }
+
+    async fn empty_trash(&self) -> Result<i64, DatabaseError> {
+        let blobs = self.blobs.clone();
+        self.storage
+            .write(move |conn| empty_trash(conn, &blobs))
+            .await
+    }
+
+    async fn purge_trash(&self, days: i64) -> Result<i64, DatabaseError> {
+        let blobs = self.blobs.clone();
+        self.storage
+            .write(move |conn| purge_trash(conn, &blobs, days))
+            .await
+    }
+
+    async fn undo_last_operation(&self) -> Result<Option<Operation>, DatabaseError> {
+        self.storage.write(|conn| undo_last_operation(conn)).await
+    }
+
+    async fn search(
+        &self,
+        text: &str,
+        filters: EntryFilters,
+        preview_length: usize,
+    ) -> Result<Vec<SearchResult>, DatabaseError> {
+        let text = text.to_string();
+        self.storage
+            .read(move |conn| search_items(conn, &text, &filters, preview_length))
+            .await
+    }
+
+    async fn prune(&self, configuration: &AppConfig) -> Result<Vec<i64>, DatabaseError> {
+        let (blobs, configuration) = (self.blobs.clone(), configuration.clone());
+        self.storage
+            .write(move |conn| prune_items(conn, &blobs, &configuration))
+            .await
+    }
+
+    async fn export(&self, path: &Path, filters: EntryFilters) -> Result<usize, DatabaseError> {
+        let (blobs, path) = (self.blobs.clone(), path.to_path_buf());
+        self.storage
+            .read(move |conn| export_archive(conn, &blobs, &path, &filters))
+            .await
+    }
+
+    async fn import(
+        &self,
+        path: &Path,
+        conflict: ImportConflict,
+    ) -> Result<ImportReport, DatabaseError> {
+        let (blobs, path) = (self.blobs.clone(), path.to_path_buf());
+        self.storage
+            .write(move |conn| import_archive(conn, &blobs, &path, conflict))
+            .await
+    }
+
+    async fn create_collection(&self, name: &str) -> Result<Collection, DatabaseError> {
+        let name = name.to_string();
+        self.storage
+            .write(move |conn| create_collection(conn, &name))
+            .await
+    }
+
+    async fn rename_collection(&self, id: i64, name: &str) -> Result<Collection, DatabaseError> {
+        let name = name.to_string();
+        self.storage
+            .write(move |conn| rename_collection(conn, id, &name))
+            .await
+    }
+
+    async fn delete_collection(&self, id: i64) -> Result<(), DatabaseError> {
+        self.storage
+            .write(move |conn| delete_collection(conn, id))
+            .await
+    }
+
+    async fn list_collections(&self) -> Result<Vec<Collection>, DatabaseError> {
+        self.storage.read(list_collections).await
+    }
+
+    async fn add_to_collection(&self, id: i64, collection_id: i64) -> Result<(), DatabaseError> {
+        self.storage
+            .write(move |conn| add_item_to_collection(conn, id, collection_id))
+            .await
+    }
+
+    async fn remove_from_collection(
+        &self,
+        id: i64,
+        collection_id: i64,
+    ) -> Result<(), DatabaseError> {
+        self.storage
+            .write(move |conn| remove_item_from_collection(conn, id, collection_id))
+            .await
+    }
+
+    async fn add_tag(&self, id: i64, tag: &str) -> Result<(), DatabaseError> {
+        let tag = tag.to_string();
+        self.storage
+            .write(move |conn| add_item_tag(conn, id, &tag))
+            .await
+    }
+
+    async fn remove_tag(&self, id: i64, tag: &str) -> Result<(), DatabaseError> {
+        let tag = tag.to_string();
+        self.storage
+            .write(move |conn| remove_item_tag(conn, id, &tag))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::operation::OperationKind;
+
+    // Function to get the IDs of the history, sorted (entries copied within the same second have no order)
+    async fn sorted_ids(store: &SqliteHistoryStore) -> Vec<i64> {
+        let mut ids = store.ids().await.unwrap();
+        ids.sort();
+        ids
+    }
+
+    // Function to insert a text with no source, no marks and deduplication enabled
+    async fn insert(store: &SqliteHistoryStore, content: &str) -> (i64, bool) {
+        store
+            .insert_text("text", content, None, &EntryMarks::default(), true)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn insert_deduplicates_identical_contents() {
+        let store = SqliteHistoryStore::in_memory().unwrap();
+        let (first, existed) = insert(&store, "hello world").await;
+        assert!(!existed);
+        let (second, _) = insert(&store, "another text").await;
+
+        let (again, existed) = insert(&store, "hello world").await;
+        assert_eq!(again, first);
+        assert!(existed);
+        assert_eq!(sorted_ids(&store).await, vec![first, second]);
+        assert_eq!(store.get(first).await.unwrap().content, "hello world");
+
+        // Without deduplication the same content is inserted again
+        let (duplicate, existed) = store
+            .insert_text("text", "hello world", None, &EntryMarks::default(), false)
+            .await
+            .unwrap();
+        assert_ne!(duplicate, first);
+        assert!(!existed);
+    }
+
+    #[tokio::test]
+    async fn insert_stores_the_marks_and_refreshes_them_on_deduplication() {
+        let store = SqliteHistoryStore::in_memory().unwrap();
+        let marks = EntryMarks {
+            sensitive: Some("password".to_string()),
+            masked_content: Some("password=•••••••".to_string()),
+            expiry_minutes: Some(5),
+        };
+        let (id, _) = store
+            .insert_text("text", "password=hunter2", None, &marks, true)
+            .await
+            .unwrap();
+        let preview = store.preview(id, 100).await.unwrap();
+        assert_eq!(preview.content, "password=•••••••");
+        assert_eq!(preview.sensitive.as_deref(), Some("password"));
+        assert!(preview.expires_at.is_some());
+
+        let (again, _) = insert(&store, "password=hunter2").await;
+        assert_eq!(again, id);
+        let preview = store.preview(id, 100).await.unwrap();
+        assert_eq!(preview.content, "password=hunter2");
+        assert_eq!(preview.sensitive, None);
+        assert_eq!(preview.expires_at, None);
+    }
+
+    #[tokio::test]
+    async fn list_pages_follow_the_cursor() {
+        let store = SqliteHistoryStore::in_memory().unwrap();
+        let mut inserted = Vec::new();
+        for i in 0..5 {
+            inserted.push(insert(&store, &format!("entry {}", i)).await.0);
+        }
+        store.set_pinned(inserted[0], true).await.unwrap();
+
+        let mut listed = Vec::new();
+        let mut cursor: Option<ListCursor> = None;
+        loop {
+            let page = store
+                .list(cursor, 2, EntrySort::Date, EntryFilters::default(), 50)
+                .await
+                .unwrap();
+            assert_eq!(page.total, 5);
+            assert!(page.entries.len() <= 2);
+            listed.extend(page.entries.iter().map(|e| e.id));
+            match page.next_cursor {
+                Some(next) => cursor = Some(serde_json::from_str(&next).unwrap()),
+                None => break,
+            }
+        }
+
+        // The pinned entry comes first, then the most recent ones
+        let mut expected = vec![inserted[0]];
+        expected.extend(inserted[1..].iter().rev());
+        assert_eq!(listed, expected);
+    }
+
+    #[tokio::test]
+    async fn usage_moves_the_entry_to_the_top_when_asked() {
+        let store = SqliteHistoryStore::in_memory().unwrap();
+        let (first, _) = insert(&store, "first").await;
+        // The dates have a one second resolution
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let (second, _) = insert(&store, "second").await;
+
+        store.record_usage(first, false).await.unwrap();
+        assert_eq!(store.ids().await.unwrap(), vec![second, first]);
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        store.record_usage(first, true).await.unwrap();
+        assert_eq!(store.ids().await.unwrap(), vec![first, second]);
+        assert_eq!(store.get(first).await.unwrap().paste_count, 2);
+    }
+
+    #[tokio::test]
+    async fn trashed_entries_come_back_on_undo() {
+        let store = SqliteHistoryStore::in_memory().unwrap();
+        let (first, _) = insert(&store, "first").await;
+        let (second, _) = insert(&store, "second").await;
+
+        store.trash(first).await.unwrap();
+        assert_eq!(sorted_ids(&store).await, vec![second]);
+
+        let operation = store.undo_last_operation().await.unwrap().unwrap();
+        assert_eq!(operation.kind, OperationKind::Delete);
+        assert_eq!(operation.entry_ids, vec![first]);
+        assert_eq!(sorted_ids(&store).await, vec![first, second]);
+
+        store.trash_all().await.unwrap();
+        assert!(store.ids().await.unwrap().is_empty());
+        store.undo_last_operation().await.unwrap();
+        assert_eq!(sorted_ids(&store).await, vec![first, second]);
+        assert!(store.undo_last_operation().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn search_finds_the_matching_entries() {
+        let store = SqliteHistoryStore::in_memory().unwrap();
+        let (query, _) = insert(&store, "SELECT * FROM orders WHERE total > 100").await;
+        insert(&store, "Meeting notes for tomorrow").await;
+        let (trashed, _) = insert(&store, "orders to ship").await;
+        store.trash(trashed).await.unwrap();
+
+        let results = store
+            .search("orders", EntryFilters::default(), 50)
+            .await
+            .unwrap();
+        assert_eq!(
+            results.iter().map(|r| r.id).collect::<Vec<_>>(),
+            vec![query]
+        );
+        assert!(results[0]
+            .snippet
+            .iter()
+            .any(|part| part.highlighted && part.text == "orders"));
+
+        assert!(store
+            .search("nothing like this", EntryFilters::default(), 50)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,469 @@
+use rusqlite::Connection;
+
+use crate::core::{
+    blob_store::BlobStore,
+    database_api::{content_hash, frecency_event},
+};
+
+// Struct to represent a schema migration, identified by the version it upgrades the database to
+struct Migration {
+    version: i64,
+    description: &'static str,
+    up: fn(&Connection, &BlobStore) -> rusqlite::Result<()>, // Some migrations move files in the blob store
+}
+
+// Ordered list of the migrations, the version of the database is stored in `PRAGMA user_version`
+// To change the schema, append a new migration here (never edit one that has already been released)
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create the clipboard entries table",
+        up: create_entries_table,
+    },
+    Migration {
+        version: 2,
+        description: "create the full-text search index",
+        up: create_search_index,
+    },
+    Migration {
+        version: 3,
+        description: "add the content hash used to deduplicate entries",
+        up: add_content_hash,
+    },
+    Migration {
+        version: 4,
+        description: "add the usage counter and the indexes used to list the history",
+        up: add_listing_indexes,
+    },
+    Migration {
+        version: 5,
+        description: "move the images to the blob store",
+        up: move_images_to_blob_store,
+    },
+    Migration {
+        version: 6,
+        description: "create the table of the encryption keys",
+        up: create_encryption_keys_table,
+    },
+    Migration {
+        version: 7,
+        description: "create the collections and tags tables",
+        up: create_collections_and_tags,
+    },
+    Migration {
+        version: 8,
+        description: "add the usage log and the frecency score",
+        up: add_usage_tracking,
+    },
+    Migration {
+        version: 9,
+        description: "add the trash and the journal of the operations",
+        up: add_trash,
+    },
+    Migration {
+        version: 10,
+        description: "add the application the entries were copied from",
+        up: add_source_app,
+    },
+    Migration {
+        version: 11,
+        description: "add the titles and notes, indexed for the search",
+        up: add_titles_and_notes,
+    },
+    Migration {
+        version: 12,
+        description: "add the template flag",
+        up: add_template_flag,
+    },
+    Migration {
+        version: 13,
+        description: "add the sensitive content marks and the expiry date",
+        up: add_sensitive_marks,
+    },
+    Migration {
+        version: 14,
+        description: "add the compressed content of the large texts",
+        up: add_compressed_content,
+    },
+];
+
+// Function to get the version of the most recent schema
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+// Function to bring the database schema up to date
+// Every migration runs in its own transaction along with the version bump, so a failure leaves the
+// database at the previous version instead of a half-upgraded state
+pub fn run_migrations(
+    conn: &mut Connection,
+    blobs: &BlobStore,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let current: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    // Refuse to touch a database created by a more recent version of the application
+    if current > latest_version() {
+        return Err(format!(
+            "database schema version {} is newer than the supported version {}",
+            current,
+            latest_version()
+        )
+        .into());
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn.transaction()?;
+
+        (migration.up)(&tx, blobs).map_err(|e| {
+            format!(
+                "migration {} ({}) failed: {}",
+                migration.version, migration.description, e
+            )
+        })?;
+
+        // PRAGMA does not accept bound parameters, the version is a trusted integer
+        tx.execute_batch(&format!("PRAGMA user_version = {}", migration.version))?;
+        tx.commit()?;
+
+        println!(
+            "Applied migration {}: {}",
+            migration.version, migration.description
+        );
+    }
+
+    Ok(())
+}
+
+// Version 1: the original table, databases created before the migrations may miss some columns
+fn create_entries_table(conn: &Connection, _: &BlobStore) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS clipboard_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            type TEXT NOT NULL,
+            content TEXT NOT NULL,
+            added_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            pinned INTEGER NOT NULL DEFAULT 0,
+            forced_language TEXT DEFAULT NULL
+        )",
+        [],
+    )?;
+
+    let columns = vec![
+        "type TEXT NOT NULL",
+        "content TEXT NOT NULL",
+        "added_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP",
+        "pinned INTEGER NOT NULL DEFAULT 0",
+        "forced_language TEXT DEFAULT NULL",
+    ];
+
+    let mut stmt = conn.prepare("PRAGMA table_info(clipboard_entries)")?;
+    let column_info: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .collect();
+
+    for column in columns {
+        let column_name = column.split_whitespace().next().unwrap();
+        if !column_info.contains(&column_name.to_string()) {
+            conn.execute(
+                &format!("ALTER TABLE clipboard_entries ADD COLUMN {}", column),
+                [],
+            )?;
+            println!("Added missing column: {}", column);
+        }
+    }
+
+    Ok(())
+}
+
+// Version 2: full-text search index (FTS5) over the entries content, images are never indexed
+fn create_search_index(conn: &Connection, _: &BlobStore) -> rusqlite::Result<()> {
+    // The index may already exist on databases created before the migrations
+    conn.execute_batch("DROP TABLE IF EXISTS clipboard_entries_fts")?;
+
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE clipboard_entries_fts USING fts5(
+            content,
+            content='clipboard_entries',
+            content_rowid='id',
+            tokenize='unicode61 remove_diacritics 2'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS clipboard_entries_fts_insert AFTER INSERT ON clipboard_entries BEGIN
+            INSERT INTO clipboard_entries_fts(rowid, content)
+                SELECT new.id, new.content WHERE new.type != 'image';
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS clipboard_entries_fts_delete AFTER DELETE ON clipboard_entries BEGIN
+            INSERT INTO clipboard_entries_fts(clipboard_entries_fts, rowid, content)
+                SELECT 'delete', old.id, old.content WHERE old.type != 'image';
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS clipboard_entries_fts_update AFTER UPDATE OF type, content ON clipboard_entries BEGIN
+            INSERT INTO clipboard_entries_fts(clipboard_entries_fts, rowid, content)
+                SELECT 'delete', old.id, old.content WHERE old.type != 'image';
+            INSERT INTO clipboard_entries_fts(rowid, content)
+                SELECT new.id, new.content WHERE new.type != 'image';
+        END;
+
+        INSERT INTO clipboard_entries_fts(rowid, content)
+            SELECT id, content FROM clipboard_entries WHERE type != 'image';",
+    )
+}
+
+// Version 3: hash of the content of each entry, indexed to find duplicates quickly
+fn add_content_hash(conn: &Connection, _: &BlobStore) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN content_hash TEXT DEFAULT NULL;
+        CREATE INDEX idx_clipboard_entries_content_hash ON clipboard_entries(content_hash);",
+    )?;
+
+    // Hash the existing entries
+    let mut select = conn.prepare("SELECT id, content FROM clipboard_entries")?;
+    let mut update =
+        conn.prepare("UPDATE clipboard_entries SET content_hash = ?1 WHERE id = ?2")?;
+    let rows = select.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for row in rows {
+        let (id, content) = row?;
+        update.execute((content_hash(&content), id))?;
+    }
+
+    Ok(())
+}
+
+// Version 4: usage counter and indexes backing the paginated listing
+fn add_listing_indexes(conn: &Connection, _: &BlobStore) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN paste_count INTEGER NOT NULL DEFAULT 0;
+        CREATE INDEX idx_clipboard_entries_added_at ON clipboard_entries(added_at);
+        CREATE INDEX idx_clipboard_entries_pinned_added_at ON clipboard_entries(pinned, added_at, id);
+        CREATE INDEX idx_clipboard_entries_type ON clipboard_entries(type, added_at);",
+    )
+}
+
+// Version 5: images are stored as PNG files in the blob store, the row only keeps their metadata
+fn move_images_to_blob_store(conn: &Connection, blobs: &BlobStore) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN byte_size INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE clipboard_entries ADD COLUMN blob_hash TEXT DEFAULT NULL;
+        ALTER TABLE clipboard_entries ADD COLUMN image_width INTEGER DEFAULT NULL;
+        ALTER TABLE clipboard_entries ADD COLUMN image_height INTEGER DEFAULT NULL;
+        ALTER TABLE clipboard_entries ADD COLUMN mime_type TEXT DEFAULT NULL;
+        CREATE INDEX idx_clipboard_entries_blob_hash ON clipboard_entries(blob_hash);
+        UPDATE clipboard_entries SET byte_size = length(CAST(content AS BLOB)) WHERE type != 'image';",
+    )?;
+
+    let mut select =
+        conn.prepare("SELECT id, content FROM clipboard_entries WHERE type = 'image'")?;
+    let mut update = conn.prepare(
+        "UPDATE clipboard_entries
+        SET content = '', content_hash = ?1, byte_size = ?2, blob_hash = ?1, image_width = ?3, image_height = ?4, mime_type = ?5
+        WHERE id = ?6",
+    )?;
+    let rows = select.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+    })?;
+
+    for row in rows {
+        let (id, content) = row?;
+        match blobs.store_image(&content) {
+            Ok(blob) => {
+                update.execute((
+                    &blob.hash,
+                    blob.byte_size,
+                    blob.width,
+                    blob.height,
+                    blob.mime_type,
+                    id,
+                ))?;
+            }
+            // Leave unreadable images untouched rather than losing them
+            Err(e) => eprintln!("Failed to move image entry {} to the blob store: {}", id, e),
+        }
+    }
+
+    Ok(())
+}
+
+// Version 6: keys of the encrypted blobs, protected by the encryption of the database itself
+fn create_encryption_keys_table(conn: &Connection, _: &BlobStore) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE encryption_keys (
+            name TEXT PRIMARY KEY,
+            key BLOB NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+// Version 7: named collections (an entry can belong to several of them) and free-form tags
+// Foreign keys are not enforced on the connections, triggers remove the links of deleted rows instead
+fn create_collections_and_tags(conn: &Connection, _: &BlobStore) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE collections (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE entry_collections (
+            entry_id INTEGER NOT NULL REFERENCES clipboard_entries(id) ON DELETE CASCADE,
+            collection_id INTEGER NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+            PRIMARY KEY (entry_id, collection_id)
+        ) WITHOUT ROWID;
+        CREATE INDEX idx_entry_collections_collection ON entry_collections(collection_id);
+
+        CREATE TABLE entry_tags (
+            entry_id INTEGER NOT NULL REFERENCES clipboard_entries(id) ON DELETE CASCADE,
+            tag TEXT NOT NULL COLLATE NOCASE,
+            PRIMARY KEY (entry_id, tag)
+        ) WITHOUT ROWID;
+        CREATE INDEX idx_entry_tags_tag ON entry_tags(tag);
+
+        CREATE TRIGGER clipboard_entries_links_delete AFTER DELETE ON clipboard_entries BEGIN
+            DELETE FROM entry_collections WHERE entry_id = old.id;
+            DELETE FROM entry_tags WHERE entry_id = old.id;
+        END;
+
+        CREATE TRIGGER collections_links_delete AFTER DELETE ON collections BEGIN
+            DELETE FROM entry_collections WHERE collection_id = old.id;
+        END;",
+    )
+}
+
+// Version 8: last use and frecency score of the entries, log of every use
+fn add_usage_tracking(conn: &Connection, _: &BlobStore) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN last_used_at TIMESTAMP DEFAULT NULL;
+        ALTER TABLE clipboard_entries ADD COLUMN frecency REAL NOT NULL DEFAULT 0;
+        CREATE INDEX idx_clipboard_entries_pinned_frecency ON clipboard_entries(pinned, frecency, id);
+
+        CREATE TABLE entry_usage (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entry_id INTEGER NOT NULL REFERENCES clipboard_entries(id) ON DELETE CASCADE,
+            used_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE INDEX idx_entry_usage_entry ON entry_usage(entry_id, used_at);
+
+        CREATE TRIGGER clipboard_entries_usage_delete AFTER DELETE ON clipboard_entries BEGIN
+            DELETE FROM entry_usage WHERE entry_id = old.id;
+        END;",
+    )?;
+
+    // The time of the previous uses is unknown, count them as happening when the entry was copied
+    let mut select = conn.prepare(
+        "SELECT id, julianday(added_at) - julianday('1970-01-01'), paste_count FROM clipboard_entries",
+    )?;
+    let mut update = conn.prepare("UPDATE clipboard_entries SET frecency = ?1 WHERE id = ?2")?;
+    let rows = select.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, Option<f64>>(1)?.unwrap_or_default(),
+            row.get::<_, i64>(2)?,
+        ))
+    })?;
+    for row in rows {
+        let (id, days, paste_count) = row?;
+        let frecency = frecency_event(days) + ((1 + paste_count.max(0)) as f64).log2();
+        update.execute((frecency, id))?;
+    }
+
+    Ok(())
+}
+
+// Version 9: deleted entries stay in the trash until it is emptied, destructive operations are journaled
+fn add_trash(conn: &Connection, _: &BlobStore) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+        CREATE INDEX idx_clipboard_entries_deleted_at ON clipboard_entries(deleted_at);
+
+        CREATE TABLE operations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            performed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            entry_ids TEXT NOT NULL
+        );",
+    )
+}
+
+// Version 10: window class, title and process of the application the entry was copied from
+fn add_source_app(conn: &Connection, _: &BlobStore) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN source_class TEXT DEFAULT NULL;
+        ALTER TABLE clipboard_entries ADD COLUMN source_title TEXT DEFAULT NULL;
+        ALTER TABLE clipboard_entries ADD COLUMN source_process TEXT DEFAULT NULL;",
+    )
+}
+
+// Version 11: title and note written by the user, the search index is rebuilt to include them
+// Images are indexed too, only through their title and note
+fn add_titles_and_notes(conn: &Connection, _: &BlobStore) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN title TEXT DEFAULT NULL;
+        ALTER TABLE clipboard_entries ADD COLUMN note TEXT DEFAULT NULL;
+
+        DROP TRIGGER IF EXISTS clipboard_entries_fts_insert;
+        DROP TRIGGER IF EXISTS clipboard_entries_fts_delete;
+        DROP TRIGGER IF EXISTS clipboard_entries_fts_update;
+        DROP TABLE IF EXISTS clipboard_entries_fts;
+
+        CREATE VIRTUAL TABLE clipboard_entries_fts USING fts5(
+            content,
+            title,
+            note,
+            content='clipboard_entries',
+            content_rowid='id',
+            tokenize='unicode61 remove_diacritics 2'
+        );
+
+        CREATE TRIGGER clipboard_entries_fts_insert AFTER INSERT ON clipboard_entries BEGIN
+            INSERT INTO clipboard_entries_fts(rowid, content, title, note)
+                VALUES (new.id, CASE WHEN new.type = 'image' THEN '' ELSE new.content END, new.title, new.note);
+        END;
+
+        CREATE TRIGGER clipboard_entries_fts_delete AFTER DELETE ON clipboard_entries BEGIN
+            INSERT INTO clipboard_entries_fts(clipboard_entries_fts, rowid, content, title, note)
+                VALUES ('delete', old.id, CASE WHEN old.type = 'image' THEN '' ELSE old.content END, old.title, old.note);
+        END;
+
+        CREATE TRIGGER clipboard_entries_fts_update AFTER UPDATE OF type, content, title, note ON clipboard_entries BEGIN
+            INSERT INTO clipboard_entries_fts(clipboard_entries_fts, rowid, content, title, note)
+                VALUES ('delete', old.id, CASE WHEN old.type = 'image' THEN '' ELSE old.content END, old.title, old.note);
+            INSERT INTO clipboard_entries_fts(rowid, content, title, note)
+                VALUES (new.id, CASE WHEN new.type = 'image' THEN '' ELSE new.content END, new.title, new.note);
+        END;
+
+        INSERT INTO clipboard_entries_fts(rowid, content, title, note)
+            SELECT id, CASE WHEN type = 'image' THEN '' ELSE content END, title, note FROM clipboard_entries;",
+    )
+}
+
+// Version 12: entries whose placeholders are expanded when they are pushed to the clipboard
+fn add_template_flag(conn: &Connection, _: &BlobStore) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN is_template INTEGER NOT NULL DEFAULT 0;",
+    )
+}
+
+// Version 13: category of the secret found in an entry, masked copy used for the previews, date of deletion
+fn add_sensitive_marks(conn: &Connection, _: &BlobStore) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN sensitive TEXT DEFAULT NULL;
+        ALTER TABLE clipboard_entries ADD COLUMN masked_content TEXT DEFAULT NULL;
+        ALTER TABLE clipboard_entries ADD COLUMN expires_at TIMESTAMP DEFAULT NULL;
+        CREATE INDEX idx_clipboard_entries_expires_at ON clipboard_entries(expires_at)
+            WHERE expires_at IS NOT NULL;",
+    )
+}
+
+// Version 14: compressed content of the texts too large to be stored in plain
+// `content` then only holds the beginning of the text, for the previews and the search
+fn add_compressed_content(conn: &Connection, _: &BlobStore) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN content_encoding TEXT DEFAULT NULL;
+        ALTER TABLE clipboard_entries ADD COLUMN compressed_content BLOB DEFAULT NULL;",
+    )
+}
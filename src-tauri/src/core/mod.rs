@@ -1,3 +1,21 @@
 pub mod app_handle;
+pub mod archive;
+pub mod backups;
+pub mod blob_store;
+pub mod capture_size;
+pub mod capture_state;
+pub mod clipboard_hints;
+pub mod compression;
 pub mod database_api;
+pub mod encryption;
+pub mod errors;
+pub mod history_store;
+pub mod migrations;
+pub mod paths;
+pub mod profiles;
+pub mod screen_lock;
+pub mod sensitive;
+pub mod source_app;
+pub mod storage;
 pub mod tasks;
+pub mod templates;
@@ -0,0 +1,245 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::{PoisonError, RwLock},
+};
+
+use directories::BaseDirs;
+use once_cell::sync::Lazy;
+
+// Environment variable holding a directory used for all the files of the application
+pub const HOME_VARIABLE: &str = "TACTICLIP_HOME";
+
+// Environment variable enabling the portable mode (set to 1), as does a `portable` file next to the executable
+pub const PORTABLE_VARIABLE: &str = "TACTICLIP_PORTABLE";
+
+// Name of the file next to the executable enabling the portable mode
+const PORTABLE_MARKER: &str = "portable";
+
+// Name of the profile stored at the root of the directories, the other ones live in a `profiles` subdirectory
+pub const DEFAULT_PROFILE: &str = "default";
+
+// Name of the file holding the active profile (in the configuration directory)
+const ACTIVE_PROFILE_FILE: &str = "profile";
+
+// Files of the database, moved together from the legacy location
+const DATA_FILES: &[&str] = &[
+    "database.db",
+    "database.db-wal",
+    "database.db-shm",
+    "database.key",
+    "database.salt",
+    "blobs",
+    "backups",
+];
+
+// Struct to represent the directories used by the application
+struct AppDirs {
+    data: PathBuf,   // Database, images and backups
+    config: PathBuf, // Configuration file
+    default: bool,   // Neither overridden by TACTICLIP_HOME nor portable, legacy files are migrated
+}
+
+// Directories resolved once at startup
+static APP_DIRS: Lazy<AppDirs> = Lazy::new(resolve_dirs);
+
+// Profile whose history and configuration are in use, restored from the previous run
+static ACTIVE_PROFILE: Lazy<RwLock<String>> = Lazy::new(|| {
+    let name = fs::read_to_string(APP_DIRS.config.join(ACTIVE_PROFILE_FILE))
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| get_profile_data_dir(name).is_dir())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+    RwLock::new(name)
+});
+
+// Function to get the directory of the executable when the portable mode is enabled
+fn portable_dir() -> Option<PathBuf> {
+    let exe_dir = env::current_exe().ok()?.parent()?.to_path_buf();
+    let enabled = env::var(PORTABLE_VARIABLE).is_ok_and(|v| v == "1" || v == "true")
+        || exe_dir.join(PORTABLE_MARKER).exists();
+    enabled.then(|| exe_dir.join("data"))
+}
+
+// Function to find the directories of the application
+// TACTICLIP_HOME comes first, then the portable mode, then the standard locations of the platform:
+// $XDG_DATA_HOME/tacticlip and $XDG_CONFIG_HOME/tacticlip on Linux, Application Support on macOS
+// and AppData\Local\PetchouSoftware\TactiClip on Windows
+fn resolve_dirs() -> AppDirs {
+    if let Some(home) = env::var_os(HOME_VARIABLE).filter(|v| !v.is_empty()) {
+        let home = PathBuf::from(home);
+        return AppDirs {
+            data: home.clone(),
+            config: home,
+            default: false,
+        };
+    }
+
+    if let Some(dir) = portable_dir() {
+        return AppDirs {
+            data: dir.clone(),
+            config: dir,
+            default: false,
+        };
+    }
+
+    let base_dirs = BaseDirs::new().expect("Unable to access directories");
+    let (data, config) = if cfg!(target_os = "windows") {
+        let dir = base_dirs
+            .data_local_dir()
+            .join("PetchouSoftware")
+            .join("TactiClip");
+        (dir.clone(), dir)
+    } else if cfg!(target_os = "macos") {
+        let dir = base_dirs.data_dir().join("TactiClip");
+        (dir.clone(), dir)
+    } else {
+        (
+            base_dirs.data_dir().join("tacticlip"),
+            base_dirs.config_dir().join("tacticlip"),
+        )
+    };
+
+    AppDirs {
+        data,
+        config,
+        default: true,
+    }
+}
+
+// Function to get the directory holding the database, the images and the backups
+pub fn get_data_dir() -> PathBuf {
+    APP_DIRS.data.clone()
+}
+
+// Function to get the directory holding the profiles other than the default one (they have the same layout)
+pub fn get_profiles_dir() -> PathBuf {
+    APP_DIRS.data.join("profiles")
+}
+
+// Function to get the data directory of a profile
+pub fn get_profile_data_dir(name: &str) -> PathBuf {
+    match name {
+        DEFAULT_PROFILE => APP_DIRS.data.clone(),
+        _ => get_profiles_dir().join(name),
+    }
+}
+
+// Function to get the configuration directory of a profile
+pub fn get_profile_config_dir(name: &str) -> PathBuf {
+    match name {
+        DEFAULT_PROFILE => APP_DIRS.config.clone(),
+        _ => APP_DIRS.config.join("profiles").join(name),
+    }
+}
+
+// Function to get the name of the active profile
+pub fn active_profile() -> String {
+    ACTIVE_PROFILE
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+// Function to change the active profile, remembered for the next runs
+pub fn set_active_profile(name: &str) -> io::Result<()> {
+    fs::create_dir_all(&APP_DIRS.config)?;
+    fs::write(APP_DIRS.config.join(ACTIVE_PROFILE_FILE), name)?;
+    *ACTIVE_PROFILE
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = name.to_string();
+    Ok(())
+}
+
+// Function to get the path of the database of the active profile
+pub fn get_db_path() -> PathBuf {
+    get_profile_data_dir(&active_profile()).join("database.db")
+}
+
+// Function to get the path of the configuration file of the active profile
+pub fn get_config_path() -> PathBuf {
+    get_profile_config_dir(&active_profile()).join("config.json")
+}
+
+// Function to get the directory used by the previous versions on macOS and Linux
+fn legacy_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        return None;
+    }
+    let base_dirs = BaseDirs::new()?;
+    Some(
+        base_dirs
+            .home_dir()
+            .join(".local")
+            .join("PetchouSoftware")
+            .join("TactiClip"),
+    )
+}
+
+// Function to copy a file or a directory, recursively
+fn copy_recursively(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursively(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+// Function to move a file or a directory, copied then deleted when it goes to another file system
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    copy_recursively(from, to)?;
+    if from.is_dir() {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    }
+}
+
+// Function to move the files left by a previous version in ~/.local/PetchouSoftware/TactiClip to the current directories
+// Must run before the configuration and the database are opened, files already present at the destination are kept
+pub fn migrate_legacy_files() {
+    if !APP_DIRS.default {
+        return;
+    }
+    let Some(legacy) = legacy_dir().filter(|dir| dir.is_dir()) else {
+        return;
+    };
+
+    let mut moves: Vec<(PathBuf, PathBuf)> = vec![(
+        legacy.join("config.json"),
+        APP_DIRS.config.join("config.json"),
+    )];
+    moves.extend(
+        DATA_FILES
+            .iter()
+            .map(|name| (legacy.join(name), get_data_dir().join(name))),
+    );
+
+    for (from, to) in moves {
+        if !from.exists() || to.exists() {
+            continue;
+        }
+        match move_path(&from, &to) {
+            Ok(()) => println!("Moved {:?} to {:?}", from, to),
+            Err(e) => eprintln!("Failed to move {:?} to {:?}: {}", from, to, e),
+        }
+    }
+
+    // Only removed once empty, unknown files are left in place
+    let _ = fs::remove_dir(&legacy);
+    if let Some(parent) = legacy.parent() {
+        let _ = fs::remove_dir(parent);
+    }
+}
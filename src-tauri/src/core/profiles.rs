@@ -0,0 +1,387 @@
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    core::{
+        compression::CompressedText,
+        errors::DatabaseError,
+        history_store::{HistoryStore, SqliteHistoryStore},
+        paths::{
+            active_profile, get_db_path, get_profile_config_dir, get_profile_data_dir,
+            get_profiles_dir, set_active_profile, DEFAULT_PROFILE,
+        },
+    },
+    structures::{
+        archive::{ImportConflict, ImportReport},
+        clipboard_entry::ClipboardEntry,
+        collection::Collection,
+        config::{config, unload_config, AppConfig},
+        entry_filters::EntryFilters,
+        entry_marks::EntryMarks,
+        entry_page::{EntryPage, EntrySort, ListCursor},
+        operation::Operation,
+        profile::Profile,
+        search_result::SearchResult,
+        source_app::SourceApp,
+    },
+};
+
+// Maximum length of the name of a profile
+const MAX_NAME_LENGTH: usize = 32;
+
+// Function to check the name of a profile, used as a directory name
+fn check_profile_name(name: &str) -> Result<(), DatabaseError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(DatabaseError::InvalidInput(format!(
+            "A profile name must have 1 to {} letters, digits, '-' or '_'",
+            MAX_NAME_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+// Function to check that a profile exists
+pub fn ensure_profile_exists(name: &str) -> Result<(), DatabaseError> {
+    check_profile_name(name)?;
+    if name != DEFAULT_PROFILE && !get_profile_data_dir(name).is_dir() {
+        return Err(DatabaseError::InvalidInput(format!(
+            "The profile {} doesn't exist",
+            name
+        )));
+    }
+    Ok(())
+}
+
+// Function to get all profiles, the default one first and the others sorted by name
+pub fn list_profiles() -> Vec<Profile> {
+    let mut names: Vec<String> = fs::read_dir(get_profiles_dir())
+        .map(|dir| {
+            dir.filter_map(Result::ok)
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|name| check_profile_name(name).is_ok() && name != DEFAULT_PROFILE)
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+
+    let active = active_profile();
+    names
+        .into_iter()
+        .map(|name| Profile {
+            active: name == active,
+            name,
+        })
+        .collect()
+}
+
+// Function to create an empty profile, its history and configuration are created when it is first used
+pub fn create_profile(name: &str) -> Result<Profile, DatabaseError> {
+    check_profile_name(name)?;
+    if name == DEFAULT_PROFILE || get_profile_data_dir(name).exists() {
+        return Err(DatabaseError::InvalidInput(format!(
+            "A profile named {} already exists",
+            name
+        )));
+    }
+
+    fs::create_dir_all(get_profile_data_dir(name))?;
+    fs::create_dir_all(get_profile_config_dir(name))?;
+
+    Ok(Profile {
+        name: name.to_string(),
+        active: false,
+    })
+}
+
+// Function to delete a profile along with its history, its backups and its configuration
+// The default profile and the active one can't be deleted
+pub fn delete_profile(name: &str) -> Result<(), DatabaseError> {
+    ensure_profile_exists(name)?;
+    if name == DEFAULT_PROFILE || name == active_profile() {
+        return Err(DatabaseError::InvalidInput(format!(
+            "The profile {} is in use and can't be deleted",
+            name
+        )));
+    }
+
+    for dir in [get_profile_data_dir(name), get_profile_config_dir(name)] {
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+    }
+    Ok(())
+}
+
+// Function to make a profile active, its configuration is loaded and its history replaces the current one
+// The previous profile stays active if the history of the new one can't be opened
+pub fn switch_profile(store: &ProfileStore, name: &str) -> Result<(), DatabaseError> {
+    ensure_profile_exists(name)?;
+    let previous = active_profile();
+    if name == previous {
+        return Ok(());
+    }
+
+    set_active_profile(name)?;
+    unload_config();
+
+    let configuration: AppConfig = config();
+    match SqliteHistoryStore::open(&get_db_path(), &configuration) {
+        Ok(history) => {
+            store.replace(history);
+            println!("Switched to the profile {}", name);
+            Ok(())
+        }
+        Err(e) => {
+            let _ = set_active_profile(&previous);
+            unload_config();
+            Err(e)
+        }
+    }
+}
+
+// History of the active profile, replaced when another profile is selected
+// The commands and the tasks keep the same store while the profile changes underneath
+pub struct ProfileStore {
+    current: RwLock<Arc<SqliteHistoryStore>>,
+}
+
+impl ProfileStore {
+    pub fn new(store: SqliteHistoryStore) -> ProfileStore {
+        ProfileStore {
+            current: RwLock::new(Arc::new(store)),
+        }
+    }
+
+    // Function to get the history of the active profile
+    pub fn current(&self) -> Arc<SqliteHistoryStore> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    // Function to replace the history, the previous one is closed once the requests in progress complete
+    pub fn replace(&self, store: SqliteHistoryStore) {
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(store);
+    }
+}
+
+#[async_trait]
+impl HistoryStore for ProfileStore {
+    async fn insert_text(
+        &self,
+        entry_type: &str,
+        content: &str,
+        source: Option<&SourceApp>,
+        marks: &EntryMarks,
+        deduplicate: bool,
+    ) -> Result<(i64, bool), DatabaseError> {
+        self.current()
+            .insert_text(entry_type, content, source, marks, deduplicate)
+            .await
+    }
+
+    async fn insert_compressed_text(
+        &self,
+        entry_type: &str,
+        text: CompressedText,
+        source: Option<&SourceApp>,
+        marks: &EntryMarks,
+        deduplicate: bool,
+    ) -> Result<(i64, bool), DatabaseError> {
+        self.current()
+            .insert_compressed_text(entry_type, text, source, marks, deduplicate)
+            .await
+    }
+
+    async fn insert_image(
+        &self,
+        base64_image: &str,
+        source: Option<&SourceApp>,
+        marks: &EntryMarks,
+        deduplicate: bool,
+    ) -> Result<(i64, bool), DatabaseError> {
+        self.current()
+            .insert_image(base64_image, source, marks, deduplicate)
+            .await
+    }
+
+    async fn get(&self, id: i64) -> Result<ClipboardEntry, DatabaseError> {
+        self.current().get(id).await
+    }
+
+    async fn preview(
+        &self,
+        id: i64,
+        max_characters: usize,
+    ) -> Result<ClipboardEntry, DatabaseError> {
+        self.current().preview(id, max_characters).await
+    }
+
+    async fn last_of_type(
+        &self,
+        entry_type: &str,
+    ) -> Result<Option<ClipboardEntry>, DatabaseError> {
+        self.current().last_of_type(entry_type).await
+    }
+
+    async fn ids(&self) -> Result<Vec<i64>, DatabaseError> {
+        self.current().ids().await
+    }
+
+    async fn list(
+        &self,
+        cursor: Option<ListCursor>,
+        limit: i64,
+        sort: EntrySort,
+        filters: EntryFilters,
+        max_characters: usize,
+    ) -> Result<EntryPage, DatabaseError> {
+        self.current()
+            .list(cursor, limit, sort, filters, max_characters)
+            .await
+    }
+
+    async fn set_pinned(&self, id: i64, pinned: bool) -> Result<(), DatabaseError> {
+        self.current().set_pinned(id, pinned).await
+    }
+
+    async fn unpin_all(&self) -> Result<i64, DatabaseError> {
+        self.current().unpin_all().await
+    }
+
+    async fn set_forced_language(&self, id: i64, language: &str) -> Result<(), DatabaseError> {
+        self.current().set_forced_language(id, language).await
+    }
+
+    async fn set_title(&self, id: i64, title: Option<&str>) -> Result<(), DatabaseError> {
+        self.current().set_title(id, title).await
+    }
+
+    async fn set_note(&self, id: i64, note: Option<&str>) -> Result<(), DatabaseError> {
+        self.current().set_note(id, note).await
+    }
+
+    async fn set_template(&self, id: i64, is_template: bool) -> Result<(), DatabaseError> {
+        self.current().set_template(id, is_template).await
+    }
+
+    async fn set_expiry(
+        &self,
+        id: i64,
+        minutes: Option<i64>,
+    ) -> Result<Option<String>, DatabaseError> {
+        self.current().set_expiry(id, minutes).await
+    }
+
+    async fn purge_expired(&self) -> Result<Vec<i64>, DatabaseError> {
+        self.current().purge_expired().await
+    }
+
+    async fn record_usage(&self, id: i64, move_to_top: bool) -> Result<(), DatabaseError> {
+        self.current().record_usage(id, move_to_top).await
+    }
+
+    async fn delete(&self, id: i64) -> Result<(), DatabaseError> {
+        self.current().delete(id).await
+    }
+
+    async fn trash(&self, id: i64) -> Result<(), DatabaseError> {
+        self.current().trash(id).await
+    }
+
+    async fn trash_all(&self) -> Result<i64, DatabaseError> {
+        self.current().trash_all().await
+    }
+
+    async fn restore(&self, id: i64) -> Result<(), DatabaseError> {
+        self.current().restore(id).await
+    }
+
+    async fn empty_trash(&self) -> Result<i64, DatabaseError> {
+        self.current().empty_trash().await
+    }
+
+    async fn purge_trash(&self, days: i64) -> Result<i64, DatabaseError> {
+        self.current().purge_trash(days).await
+    }
+
+    async fn undo_last_operation(&self) -> Result<Option<Operation>, DatabaseError> {
+        self.current().undo_last_operation().await
+    }
+
+    async fn search(
+        &self,
+        text: &str,
+        filters: EntryFilters,
+        preview_length: usize,
+    ) -> Result<Vec<SearchResult>, DatabaseError> {
+        self.current().search(text, filters, preview_length).await
+    }
+
+    async fn prune(&self, configuration: &AppConfig) -> Result<Vec<i64>, DatabaseError> {
+        self.current().prune(configuration).await
+    }
+
+    async fn export(&self, path: &Path, filters: EntryFilters) -> Result<usize, DatabaseError> {
+        self.current().export(path, filters).await
+    }
+
+    async fn import(
+        &self,
+        path: &Path,
+        conflict: ImportConflict,
+    ) -> Result<ImportReport, DatabaseError> {
+        self.current().import(path, conflict).await
+    }
+
+    async fn create_collection(&self, name: &str) -> Result<Collection, DatabaseError> {
+        self.current().create_collection(name).await
+    }
+
+    async fn rename_collection(&self, id: i64, name: &str) -> Result<Collection, DatabaseError> {
+        self.current().rename_collection(id, name).await
+    }
+
+    async fn delete_collection(&self, id: i64) -> Result<(), DatabaseError> {
+        self.current().delete_collection(id).await
+    }
+
+    async fn list_collections(&self) -> Result<Vec<Collection>, DatabaseError> {
+        self.current().list_collections().await
+    }
+
+    async fn add_to_collection(&self, id: i64, collection_id: i64) -> Result<(), DatabaseError> {
+        self.current().add_to_collection(id, collection_id).await
+    }
+
+    async fn remove_from_collection(
+        &self,
+        id: i64,
+        collection_id: i64,
+    ) -> Result<(), DatabaseError> {
+        self.current()
+            .remove_from_collection(id, collection_id)
+            .await
+    }
+
+    async fn add_tag(&self, id: i64, tag: &str) -> Result<(), DatabaseError> {
+        self.current().add_tag(id, tag).await
+    }
+
+    async fn remove_tag(&self, id: i64, tag: &str) -> Result<(), DatabaseError> {
+        self.current().remove_tag(id, tag).await
+    }
+}
@@ -0,0 +1,41 @@
+// Function to check if the screen of the session is locked, false when it can't be known
+// Relies on the LockedHint of systemd-logind, set by the lock screens of the main desktops
+#[cfg(target_os = "linux")]
+pub fn screen_locked() -> bool {
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+    std::process::Command::new("loginctl")
+        .args(["show-session", &session, "--property=LockedHint", "--value"])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "yes")
+}
+
+// Function to check if the screen of the session is locked, false when it can't be known
+// The input desktop can't be switched to while the lock screen (or the UAC prompt) is shown, and can't even be
+// opened while the secure desktop is active (access denied), any other failure is not taken for a lock
+#[cfg(target_os = "windows")]
+pub fn screen_locked() -> bool {
+    use windows::Win32::{
+        Foundation::E_ACCESSDENIED,
+        System::StationsAndDesktops::{
+            CloseDesktop, OpenInputDesktop, SwitchDesktop, DESKTOP_CONTROL_FLAGS,
+            DESKTOP_SWITCHDESKTOP,
+        },
+    };
+
+    unsafe {
+        match OpenInputDesktop(DESKTOP_CONTROL_FLAGS(0), false, DESKTOP_SWITCHDESKTOP) {
+            Ok(desktop) => {
+                let locked = SwitchDesktop(desktop).is_err();
+                let _ = CloseDesktop(desktop);
+                locked
+            }
+            Err(e) => e.code() == E_ACCESSDENIED,
+        }
+    }
+}
+
+// Function to check if the screen of the session is locked, not detected on the other platforms
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub fn screen_locked() -> bool {
+    false
+}
@@ -0,0 +1,182 @@
+use crate::structures::source_app::SourceApp;
+
+// Function to find the application owning the clipboard, None when it can't be known
+// Only X11 exposes the owner of the clipboard, Wayland and the other platforms always return None
+// The requests to the X server block, they are kept off the async runtime
+#[cfg(target_os = "linux")]
+pub async fn clipboard_owner() -> Option<SourceApp> {
+    tauri::async_runtime::spawn_blocking(x11::clipboard_owner)
+        .await
+        .ok()
+        .flatten()
+}
+
+// Function to find the application owning the clipboard, None when it can't be known
+#[cfg(not(target_os = "linux"))]
+pub async fn clipboard_owner() -> Option<SourceApp> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use std::{
+        fs,
+        sync::{Mutex, PoisonError},
+    };
+
+    use once_cell::sync::Lazy;
+    use x11rb::{
+        connection::Connection,
+        protocol::xproto::{Atom, AtomEnum, ConnectionExt, Window},
+        rust_connection::RustConnection,
+        NONE,
+    };
+
+    use crate::structures::source_app::SourceApp;
+
+    // Maximum length of a property read from a window, in 32 bits units
+    const MAX_PROPERTY_LENGTH: u32 = 1024;
+
+    // Maximum number of parents visited to find the top level window of the clipboard owner
+    const MAX_DEPTH: usize = 8;
+
+    // Connection to the X server and number of its screen, opened on the first capture and kept for the next ones
+    static CONNECTION: Lazy<Mutex<Option<(RustConnection, usize)>>> =
+        Lazy::new(|| Mutex::new(None));
+
+    // Function to get the ID of an atom
+    fn atom(conn: &RustConnection, name: &str) -> Option<Atom> {
+        Some(
+            conn.intern_atom(false, name.as_bytes())
+                .ok()?
+                .reply()
+                .ok()?
+                .atom,
+        )
+    }
+
+    // Function to read a property of a window, None if it isn't set
+    fn property(conn: &RustConnection, window: Window, property: Atom) -> Option<Vec<u8>> {
+        let reply = conn
+            .get_property(
+                false,
+                window,
+                property,
+                AtomEnum::ANY,
+                0,
+                MAX_PROPERTY_LENGTH,
+            )
+            .ok()?
+            .reply()
+            .ok()?;
+        (!reply.value.is_empty()).then_some(reply.value)
+    }
+
+    // Function to read a window ID or a number stored in a property
+    fn property_u32(conn: &RustConnection, window: Window, name: Atom) -> Option<u32> {
+        let value = property(conn, window, name)?;
+        Some(u32::from_ne_bytes(value.get(..4)?.try_into().ok()?))
+    }
+
+    // Function to get the class of a window (WM_CLASS holds the instance then the class, both NUL terminated)
+    fn window_class(conn: &RustConnection, window: Window) -> Option<String> {
+        let value = property(conn, window, AtomEnum::WM_CLASS.into())?;
+        let mut parts = value
+            .split(|b| *b == 0)
+            .map(|part| String::from_utf8_lossy(part).to_string())
+            .filter(|part| !part.is_empty());
+        let instance = parts.next();
+        parts.next().or(instance)
+    }
+
+    // Function to get the title of a window, in UTF-8 when the window manager provides it
+    fn window_title(conn: &RustConnection, window: Window) -> Option<String> {
+        let title = atom(conn, "_NET_WM_NAME")
+            .and_then(|name| property(conn, window, name))
+            .or_else(|| property(conn, window, AtomEnum::WM_NAME.into()))?;
+        Some(String::from_utf8_lossy(&title).trim().to_string()).filter(|t| !t.is_empty())
+    }
+
+    // Function to get the process ID of a window
+    fn window_pid(conn: &RustConnection, window: Window) -> Option<u32> {
+        property_u32(conn, window, atom(conn, "_NET_WM_PID")?)
+    }
+
+    // Function to get the name of a process
+    fn process_name(pid: u32) -> Option<String> {
+        let name = fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+        Some(name.trim().to_string()).filter(|n| !n.is_empty())
+    }
+
+    // Function to find the first window with a class among a window and its parents
+    // The clipboard is often owned by a hidden child window of the application
+    fn window_with_class(conn: &RustConnection, mut window: Window) -> Option<Window> {
+        for _ in 0..MAX_DEPTH {
+            if window_class(conn, window).is_some() {
+                return Some(window);
+            }
+            let tree = conn.query_tree(window).ok()?.reply().ok()?;
+            if tree.parent == NONE || tree.parent == tree.root {
+                return None;
+            }
+            window = tree.parent;
+        }
+        None
+    }
+
+    // Function to find the application owning the clipboard selection, through the connection kept open
+    pub fn clipboard_owner() -> Option<SourceApp> {
+        let mut connection = CONNECTION.lock().unwrap_or_else(PoisonError::into_inner);
+        if connection.is_none() {
+            *connection = x11rb::connect(None).ok();
+        }
+        let (conn, screen) = connection.as_ref()?;
+
+        // The connection is lost when the X server goes away, a new one is opened on the next capture
+        let Some(clipboard) = atom(conn, "CLIPBOARD") else {
+            *connection = None;
+            return None;
+        };
+        selection_owner(conn, *screen, clipboard)
+    }
+
+    // Function to find the application owning a selection
+    // The owner gives the class and the process, the active window gives the title when it belongs to the same
+    // application (the owner is usually a hidden window without a meaningful title)
+    fn selection_owner(conn: &RustConnection, screen: usize, selection: Atom) -> Option<SourceApp> {
+        let root = conn.setup().roots.get(screen)?.root;
+
+        let owner = conn
+            .get_selection_owner(selection)
+            .ok()?
+            .reply()
+            .ok()?
+            .owner;
+        if owner == NONE {
+            return None;
+        }
+
+        let active = atom(conn, "_NET_ACTIVE_WINDOW")
+            .and_then(|name| property_u32(conn, root, name))
+            .filter(|window| *window != NONE);
+        let window = window_with_class(conn, owner).or(active)?;
+
+        let pid = window_pid(conn, window).or_else(|| window_pid(conn, owner));
+        let class = window_class(conn, window);
+        let same_application = |active: &Window| {
+            (pid.is_some() && window_pid(conn, *active) == pid)
+                || (class.is_some() && window_class(conn, *active) == class)
+        };
+        let title = active
+            .filter(same_application)
+            .and_then(|active| window_title(conn, active))
+            .or_else(|| window_title(conn, window));
+
+        let source = SourceApp {
+            class,
+            title,
+            process: pid.and_then(process_name),
+        };
+        (!source.is_empty()).then_some(source)
+    }
+}
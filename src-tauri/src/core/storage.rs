@@ -0,0 +1,189 @@
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Mutex, PoisonError, RwLock,
+    },
+    thread,
+    time::Duration,
+};
+
+use rusqlite::Connection;
+use tokio::sync::oneshot;
+
+use crate::core::{encryption::open_with_key, errors::DatabaseError};
+
+// Number of threads serving the read requests (in WAL mode, reads never wait on the writer)
+const READER_THREADS: usize = 2;
+
+// How long a connection waits for a lock held by another connection before failing
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// A request sent to a storage thread, called with the connection of the thread (None if it couldn't be opened)
+type Job = Box<dyn FnOnce(Option<&mut Connection>) + Send>;
+
+// State shared by the reader threads, to reopen their connection when the key of the database changes
+struct ReaderState {
+    path: PathBuf,
+    key: RwLock<Option<[u8; 32]>>,
+    generation: AtomicU64,
+}
+
+// Struct to represent the storage service
+// A single thread owns the connection used for writes, a few others own read-only connections
+// The rest of the application sends them requests and awaits the replies, so no SQLite work ever
+// runs on the async runtime and no connection is ever shared between threads
+pub struct Storage {
+    writer: mpsc::Sender<Job>,
+    readers: mpsc::Sender<Job>,
+    reader_state: Option<Arc<ReaderState>>,
+}
+
+impl Storage {
+    // Function to start the storage threads, the writer takes over the given connection
+    // The readers open the database file at `path` with `key`, without a file (in-memory database)
+    // the reads are queued on the writer
+    pub fn start(
+        conn: Connection,
+        path: Option<&Path>,
+        key: Option<[u8; 32]>,
+    ) -> Result<Storage, DatabaseError> {
+        let (writer, writer_queue) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("storage-writer".to_string())
+            .spawn(move || {
+                let mut conn = conn;
+                for job in writer_queue {
+                    run_job(job, Some(&mut conn));
+                }
+            })?;
+
+        let Some(path) = path else {
+            return Ok(Storage {
+                readers: writer.clone(),
+                writer,
+                reader_state: None,
+            });
+        };
+
+        let reader_state = Arc::new(ReaderState {
+            path: path.to_path_buf(),
+            key: RwLock::new(key),
+            generation: AtomicU64::new(0),
+        });
+
+        // The reader threads share a single queue, the first idle thread takes the next request
+        let (readers, reader_queue) = mpsc::channel::<Job>();
+        let reader_queue = Arc::new(Mutex::new(reader_queue));
+        for index in 0..READER_THREADS {
+            // Open the connection up front so a failure is reported at startup
+            let conn = open_reader(&reader_state)?;
+            let state = reader_state.clone();
+            let queue = reader_queue.clone();
+
+            thread::Builder::new()
+                .name(format!("storage-reader-{}", index))
+                .spawn(move || run_reader(conn, state, queue))?;
+        }
+
+        Ok(Storage {
+            writer,
+            readers,
+            reader_state: Some(reader_state),
+        })
+    }
+
+    // Function to run a request on the writer connection
+    pub async fn write<T, F>(&self, request: F) -> Result<T, DatabaseError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, DatabaseError> + Send + 'static,
+    {
+        send(&self.writer, request).await
+    }
+
+    // Function to run a request on one of the read-only connections
+    pub async fn read<T, F>(&self, request: F) -> Result<T, DatabaseError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, DatabaseError> + Send + 'static,
+    {
+        send(&self.readers, move |conn: &mut Connection| request(conn)).await
+    }
+
+    // Function to make the readers reopen their connection with a new key (after a key rotation)
+    pub fn reopen_readers(&self, key: Option<[u8; 32]>) {
+        if let Some(state) = &self.reader_state {
+            *state.key.write().unwrap_or_else(PoisonError::into_inner) = key;
+            state.generation.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+// Function to send a request to a storage thread and wait for its reply
+async fn send<T, F>(queue: &mpsc::Sender<Job>, request: F) -> Result<T, DatabaseError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut Connection) -> Result<T, DatabaseError> + Send + 'static,
+{
+    let (reply, response) = oneshot::channel();
+    let job: Job = Box::new(move |conn| {
+        let result = match conn {
+            Some(conn) => request(conn),
+            None => Err(DatabaseError::Unavailable),
+        };
+        let _ = reply.send(result);
+    });
+
+    queue.send(job).map_err(|_| DatabaseError::Unavailable)?;
+
+    // The reply is dropped without being sent if the request panicked
+    response.await.map_err(|_| DatabaseError::Unavailable)?
+}
+
+// Function to run a request, a panic only fails the request instead of stopping the thread
+fn run_job(job: Job, conn: Option<&mut Connection>) {
+    if catch_unwind(AssertUnwindSafe(|| job(conn))).is_err() {
+        eprintln!("A storage request panicked");
+    }
+}
+
+// Function to open a read-only connection to the database
+fn open_reader(state: &ReaderState) -> Result<Connection, DatabaseError> {
+    let key = *state.key.read().unwrap_or_else(PoisonError::into_inner);
+    let conn = open_with_key(&state.path, key.as_ref()).map_err(DatabaseError::Encryption)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.execute_batch("PRAGMA query_only = ON;")?;
+    Ok(conn)
+}
+
+// Main loop of a reader thread
+fn run_reader(conn: Connection, state: Arc<ReaderState>, queue: Arc<Mutex<mpsc::Receiver<Job>>>) {
+    let mut conn = Some(conn);
+    let mut generation = state.generation.load(Ordering::SeqCst);
+
+    loop {
+        // Only hold the queue while waiting, so the other readers can pick the next request
+        let job = match queue.lock().unwrap_or_else(PoisonError::into_inner).recv() {
+            Ok(job) => job,
+            Err(_) => return, // The storage service was dropped
+        };
+
+        // The key of the database changed since the connection was opened
+        let current = state.generation.load(Ordering::SeqCst);
+        if current != generation {
+            generation = current;
+            drop(conn.take());
+            conn = match open_reader(&state) {
+                Ok(new_conn) => Some(new_conn),
+                Err(e) => {
+                    eprintln!("Failed to reopen a storage reader: {}", e);
+                    None
+                }
+            };
+        }
+
+        run_job(job, conn.as_mut());
+    }
+}
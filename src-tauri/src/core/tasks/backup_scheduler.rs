@@ -0,0 +1,27 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::time::sleep;
+
+use crate::{
+    core::profiles::ProfileStore,
+    structures::config::{config, AppConfig},
+};
+
+// Delay between two checks of the age of the last backup
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+// Function to back up the database at startup and then periodically, when the last backup is older than the interval
+pub async fn run_backup_scheduler(store: Arc<ProfileStore>) {
+    loop {
+        let configuration: AppConfig = config();
+
+        if configuration.backup_enabled {
+            // Only the active profile is backed up
+            if let Err(e) = store.current().backup_if_due(&configuration).await {
+                eprintln!("Failed to back up the database: {}", e);
+            }
+        }
+
+        sleep(CHECK_INTERVAL).await;
+    }
+}
@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use tauri::AppHandle;
+use tokio::time::sleep;
+
+use crate::{
+    core::{
+        capture_state::{end_elapsed_pause, publish_capture_state, set_screen_locked},
+        screen_lock::screen_locked,
+    },
+    structures::config::{config, AppConfig},
+};
+
+// Delay between two checks of the screen lock and of the timed pause
+const MONITOR_INTERVAL: Duration = Duration::from_secs(2);
+
+// Function to end the timed pauses and to pause the capture while the screen is locked
+pub async fn run_capture_monitor(app: AppHandle) {
+    loop {
+        let configuration: AppConfig = config();
+
+        // The lock screen is only looked for when the user asked for it
+        let locked = configuration.pause_on_screen_lock
+            && tauri::async_runtime::spawn_blocking(screen_locked)
+                .await
+                .unwrap_or(false);
+
+        let lock_changed = set_screen_locked(locked);
+        let pause_ended = end_elapsed_pause();
+        if lock_changed || pause_ended {
+            publish_capture_state(&app);
+        }
+
+        sleep(MONITOR_INTERVAL).await;
+    }
+}
@@ -1,20 +1,52 @@
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Mutex;
 use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::time::Duration;
 
 use once_cell::sync::Lazy;
-use rusqlite::Connection;
-use tauri::{AppHandle, Emitter, Manager};
+use tauri::{AppHandle, Emitter, Listener, Manager};
 use tokio::time::sleep;
 
-use crate::core::database_api::{get_last_item_copied, insert_clipboard_entry};
-use crate::structures::clipboard_entry::ClipboardEntry;
+use crate::core::capture_size::{format_size, notify_capture, truncate_text};
+use crate::core::capture_state::is_capture_paused;
+use crate::core::clipboard_hints::marked_do_not_record;
+use crate::core::compression::{compress_text, text_prefix, PREVIEW_BYTES};
+use crate::core::history_store::SharedHistoryStore;
+use crate::core::sensitive::{detect, mask, Finding};
+use crate::core::source_app::clipboard_owner;
+use crate::structures::config::{config, AppConfig, OversizedAction, SensitiveAction};
+use crate::structures::entry_marks::EntryMarks;
+use crate::structures::source_app::SourceApp;
 
 pub static LAST_TEXT: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new())); // Clipboard watcher control
-pub static LAST_IMAGE: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new())); // Clipboard watcher control
+pub static LAST_IMAGE: Lazy<Mutex<u64>> = Lazy::new(|| Mutex::new(0)); // Clipboard watcher control (fingerprint of the last image)
 pub static PUSHED_COPY: AtomicBool = AtomicBool::new(false); // Flag to indicate if the clipboard was pushed
+static CAPTURE_SUSPENSIONS: AtomicUsize = AtomicUsize::new(0); // Number of operations replacing the database, nothing is captured meanwhile
+pub static CLIPBOARD_CHANGED: AtomicBool = AtomicBool::new(true); // Flag set by the clipboard monitor when the clipboard changes
+
+// Event emitted by the monitor of the clipboard plugin when the clipboard changes
+const CLIPBOARD_UPDATE_EVENT: &str = "plugin:clipboard://clipboard-monitor/update";
 
+// Number of bytes taken at each end of an image too large to be recorded to compute its fingerprint
+const FINGERPRINT_SAMPLE_BYTES: usize = 64 * 1024;
+
+// Struct to represent a suspension of the capture, which resumes once every suspension is dropped
+// (a restore and a profile switch may overlap)
+pub struct CaptureSuspension(());
+
+// Function to stop the capture while the database is replaced, until the returned suspension is dropped
+pub fn suspend_capture() -> CaptureSuspension {
+    CAPTURE_SUSPENSIONS.fetch_add(1, Ordering::SeqCst);
+    CaptureSuspension(())
+}
+
+impl Drop for CaptureSuspension {
+    fn drop(&mut self) {
+        CAPTURE_SUSPENSIONS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
 
 // Compile a regex pattern to match color formats thread-safely)
 static COLOR_REGEX: Lazy<Regex> = Lazy::new(|| {
@@ -29,29 +61,59 @@ static MAIL_REGEX: Lazy<Regex> = Lazy::new(|| {
 });
 
 // Compile regex patterns to match URL
-static URL_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?i)^[a-z][a-z0-9+\-.]*://[^\s]+$").unwrap()
-});
+static URL_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^[a-z][a-z0-9+\-.]*://[^\s]+$").unwrap());
 
+// Function to compute a cheap fingerprint of an image, to detect changes without keeping the whole image in memory
+pub fn image_fingerprint(base64_image: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    base64_image.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Function to compute a fingerprint of an image from its size and both of its ends, for the images too large to be
+// recorded (they are never hashed whole)
+fn sampled_image_fingerprint(base64_image: &str) -> u64 {
+    let bytes = base64_image.as_bytes();
+    let sample = FINGERPRINT_SAMPLE_BYTES.min(bytes.len());
+    let mut hasher = DefaultHasher::new();
+    bytes.len().hash(&mut hasher);
+    bytes[..sample].hash(&mut hasher);
+    bytes[bytes.len() - sample..].hash(&mut hasher);
+    hasher.finish()
+}
 
 // Function to watch the clipboard for changes
-pub async fn watch_clipboard(app: AppHandle, conn_mutex: &Mutex<Connection>) {
+pub async fn watch_clipboard(app: AppHandle, store: SharedHistoryStore) {
     let clipboard = app.state::<tauri_plugin_clipboard::Clipboard>();
 
     // Initialisation des derniers éléments copiés
-    if let Some(last_image_entry) = get_last_item_copied("image") {
-        LAST_IMAGE
-            .lock()
-            .unwrap()
-            .clone_from(&last_image_entry.content);
+    if let Ok(Some(last_image_entry)) = store.last_of_type("image").await {
+        *LAST_IMAGE.lock().unwrap() = image_fingerprint(&last_image_entry.content);
     }
-    if let Some(last_text_entry) = get_last_item_copied("text") {
+    if let Ok(Some(last_text_entry)) = store.last_of_type("text").await {
         LAST_TEXT
             .lock()
             .unwrap()
             .clone_from(&last_text_entry.content);
     }
 
+    // The clipboard is only read when the monitor tells it changed, so that large contents are not read again on
+    // every tick (it is read on every tick when the monitor can't be started)
+    app.listen_any(CLIPBOARD_UPDATE_EVENT, |_| {
+        CLIPBOARD_CHANGED.store(true, Ordering::SeqCst);
+    });
+    let monitored = match clipboard.start_monitor(app.clone()) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!(
+                "Failed to start the clipboard monitor, polling the clipboard: {}",
+                e
+            );
+            false
+        }
+    };
+
     loop {
         // Prevent the watcher from catching an elment that has just been pushed from the app
         if PUSHED_COPY.load(Ordering::Relaxed) {
@@ -59,101 +121,337 @@ pub async fn watch_clipboard(app: AppHandle, conn_mutex: &Mutex<Connection>) {
             continue;
         }
 
+        // Nothing is captured while a backup is being restored or the profile switched
+        if CAPTURE_SUSPENSIONS.load(Ordering::SeqCst) > 0 {
+            sleep(Duration::from_millis(100)).await;
+            continue;
+        }
+
+        // Nothing changed since the clipboard was last read
+        if monitored && !CLIPBOARD_CHANGED.swap(false, Ordering::SeqCst) {
+            sleep(Duration::from_millis(20)).await;
+            continue;
+        }
+
+        // Nothing is recorded while the capture is paused, what is copied meanwhile is marked as seen so that it
+        // isn't recorded on resume either
+        if is_capture_paused() {
+            if let Ok(text) = clipboard.read_text() {
+                *LAST_TEXT.lock().unwrap() = text;
+            }
+            if let Ok(image) = clipboard.read_image_base64() {
+                *LAST_IMAGE.lock().unwrap() = image_fingerprint(&image);
+            }
+            sleep(Duration::from_millis(250)).await;
+            continue;
+        }
+
+        // The owner and the hints are looked up before the content is read, so that they are the ones of the
+        // content even when the clipboard changes meanwhile
+        let source = clipboard_owner().await;
+        let do_not_record = marked_do_not_record(config().ignored_clipboard_hints).await;
+
         // Lecture du texte
         if let Ok(new_text) = clipboard.read_text() {
             if new_text != *LAST_TEXT.lock().unwrap() {
-
-                // RICH TEXT support (Too experimental for now, only work an application to itself - Word to Word and
-                // I can get the fallback to plain text to work)
-
-                /* if clipboard.has_rtf().unwrap_or(false) {
-                    if let Ok(rtf) = clipboard.read_rtf() {
-                        let combined = serde_json::json!({
-                            "plain": new_text,
-                            "rtf": rtf
-                        });
-                        stored_content = combined.to_string();
-                        format = "rich_text";
-                    }
-                } */
-
-
-                // Check if the text is a color format
-                let trimmed = new_text.trim_matches(|c: char| c.is_control() || c.is_whitespace());
-                let is_color = COLOR_REGEX.is_match(trimmed);
-                let is_email = MAIL_REGEX.is_match(trimmed);
-                let is_url = URL_REGEX.is_match(trimmed);
-                let format = if is_color {
-                    "color"
-                } else if is_email {
-                    "email"
-                } else if is_url {
-                    "url"
-                } else {
-                    "text"
-                };
-
-                let clipboard_text = if format == "text" {new_text.clone()} else { trimmed.to_string() };
-
-                *LAST_TEXT.lock().unwrap() = new_text.clone();
-                let id = insert_clipboard_entry(format, &clipboard_text, 0);
-
-                let conn = conn_mutex.lock().unwrap();
-                if let Ok(row) = conn.query_row(
-                    "SELECT id, type, content, added_at, pinned FROM clipboard_entries WHERE id = ?1",
-                    (id,),
-                    |row| {
-                        Ok(ClipboardEntry {
-                            id: row.get(0)?,
-                            entry_type: row.get(1)?,
-                            content: row.get(2)?,
-                            added_at: row.get::<_, String>(3)?,
-                            pinned: row.get::<_, i32>(4)? != 0,
-                            forced_language: row.get::<_, Option<String>>(5).unwrap_or(None),
-                        })
-                    },
-                ) {
-                    let truncated = if row.content.len() > 250 {
-                        format!("{}...", &row.content[..250])
-                    } else {
-                        row.content.clone()
-                    };
-                    let row = ClipboardEntry {
-                        content: truncated,
-                        ..row
-                    };
-                    let _ = app.emit("new-clipboard-item", row);
-                }
+                capture_text(&app, &store, new_text, source.as_ref(), do_not_record).await;
             }
         }
 
         // Lecture de l'image en base64
         if let Ok(new_image_base64) = clipboard.read_image_base64() {
-            if new_image_base64 != LAST_IMAGE.lock().unwrap().clone() {
-                LAST_IMAGE.lock().unwrap().clone_from(&new_image_base64);
-
-                let id = insert_clipboard_entry("image", &new_image_base64, 0);
-
-                let conn = conn_mutex.lock().unwrap();
-                if let Ok(row) = conn.query_row(
-                    "SELECT id, type, content, added_at, pinned FROM clipboard_entries WHERE id = ?1",
-                    (id,),
-                    |row| {
-                        Ok(ClipboardEntry {
-                            id: row.get(0)?,
-                            entry_type: row.get(1)?,
-                            content: row.get(2)?,
-                            added_at: row.get::<_, String>(3)?,
-                            pinned: row.get::<_, i32>(4)? != 0,
-                            forced_language: row.get::<_, Option<String>>(5).unwrap_or(None),
-                        })
-                    },
-                ) {
-                    let _ = app.emit("new-clipboard-item", row);
+            capture_image(
+                &app,
+                &store,
+                &new_image_base64,
+                source.as_ref(),
+                do_not_record,
+            )
+            .await;
+        }
+
+        sleep(Duration::from_millis(20)).await;
+    }
+}
+
+// Function to record a new text of the clipboard, unless it is marked as not to be recorded, too large or a secret
+// to skip
+async fn capture_text(
+    app: &AppHandle,
+    store: &SharedHistoryStore,
+    new_text: String,
+    source: Option<&SourceApp>,
+    do_not_record: bool,
+) {
+    // RICH TEXT support (Too experimental for now, only work an application to itself - Word to Word and
+    // I can get the fallback to plain text to work)
+
+    /* if clipboard.has_rtf().unwrap_or(false) {
+        if let Ok(rtf) = clipboard.read_rtf() {
+            let combined = serde_json::json!({
+                "plain": new_text,
+                "rtf": rtf
+            });
+            stored_content = combined.to_string();
+            format = "rich_text";
+        }
+    } */
+
+    // Check if the text is a color format
+    let trimmed = new_text.trim_matches(|c: char| c.is_control() || c.is_whitespace());
+    let is_color = COLOR_REGEX.is_match(trimmed);
+    let is_email = MAIL_REGEX.is_match(trimmed);
+    let is_url = URL_REGEX.is_match(trimmed);
+    let format = if is_color {
+        "color"
+    } else if is_email {
+        "email"
+    } else if is_url {
+        "url"
+    } else {
+        "text"
+    };
+
+    let mut clipboard_text = if format == "text" {
+        new_text.clone()
+    } else {
+        trimmed.to_string()
+    };
+
+    *LAST_TEXT.lock().unwrap() = new_text;
+    let configuration: AppConfig = config();
+
+    // Password managers mark their copies so that they are not recorded
+    if do_not_record {
+        return;
+    }
+
+    // Texts larger than the limit of their type are skipped, truncated or compressed
+    let mut compress_limit: Option<usize> = None;
+    if let Some(limit) = configuration.capture_limit(format) {
+        let max_bytes = (limit.max_size_kb as usize).saturating_mul(1024);
+        let size = clipboard_text.len();
+        if size > max_bytes {
+            match limit.action {
+                OversizedAction::Skip => {
+                    notify_capture(
+                        app,
+                        &format!(
+                            "A copied text of {} was not recorded, the limit is {}",
+                            format_size(size),
+                            format_size(max_bytes)
+                        ),
+                    );
+                    return;
+                }
+                OversizedAction::Truncate => {
+                    clipboard_text = truncate_text(&clipboard_text, max_bytes);
+                    notify_capture(
+                        app,
+                        &format!(
+                            "A copied text of {} was truncated to {}, the limit of its type",
+                            format_size(size),
+                            format_size(max_bytes)
+                        ),
+                    );
                 }
+                OversizedAction::Compress => compress_limit = Some(max_bytes),
             }
         }
+    }
 
-        sleep(Duration::from_millis(20)).await;
+    // Look for secrets off the async runtime, the strictest action of the categories found applies
+    // A compressed text is only looked at up to its limit (and at least its preview), like a truncated one
+    let findings = if configuration.sensitive_detection_enabled {
+        let scanned = match compress_limit {
+            Some(max_bytes) => text_prefix(&clipboard_text, max_bytes.max(PREVIEW_BYTES)),
+            None => clipboard_text.as_str(),
+        }
+        .to_string();
+        match tauri::async_runtime::spawn_blocking(move || detect(&scanned)).await {
+            Ok(findings) => findings,
+            Err(e) => {
+                eprintln!("Failed to look for secrets in clipboard text: {}", e);
+                return;
+            }
+        }
+    } else {
+        Vec::new()
+    };
+    let strictest = findings
+        .iter()
+        .map(|f| {
+            (
+                configuration.sensitive_action(f.category.as_str()),
+                f.category,
+            )
+        })
+        .max_by_key(|(action, _)| *action);
+    if matches!(strictest, Some((SensitiveAction::Skip, _))) {
+        return;
+    }
+
+    // The secret is masked before the entry is stored, so it never reaches the window
+    let mut marks = EntryMarks::default();
+    if let Some((action, category)) =
+        strictest.filter(|(action, _)| *action != SensitiveAction::Store)
+    {
+        // Only the beginning of a compressed text is shown, it is masked alone
+        let shown = match compress_limit {
+            Some(_) => text_prefix(&clipboard_text, PREVIEW_BYTES),
+            None => clipboard_text.as_str(),
+        };
+        let shown_findings: Vec<Finding> = findings
+            .iter()
+            .filter(|f| f.range.start < shown.len())
+            .map(|f| Finding {
+                category: f.category,
+                range: f.range.start..f.range.end.min(shown.len()),
+            })
+            .collect();
+        marks.sensitive = Some(category.as_str().to_string());
+        marks.masked_content = Some(mask(shown, &shown_findings));
+        marks.expiry_minutes =
+            (action == SensitiveAction::Expire).then_some(configuration.sensitive_expiry_minutes);
+    }
+
+    // Otherwise the entry gets the lifetime of the first rule matching it or of its type
+    if marks.expiry_minutes.is_none() {
+        marks.expiry_minutes = configuration.default_expiry(format, Some(&clipboard_text));
+    }
+
+    let deduplicate = configuration.deduplicate_history;
+    let inserted = match compress_limit {
+        None => {
+            store
+                .insert_text(format, &clipboard_text, source, &marks, deduplicate)
+                .await
+        }
+        Some(max_bytes) => {
+            // Compressing a large text takes a while, keep it off the async runtime
+            let text = clipboard_text.clone();
+            let compressed = tauri::async_runtime::spawn_blocking(move || compress_text(&text))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|compressed| compressed);
+            let compressed = match compressed {
+                Ok(compressed) => compressed,
+                Err(e) => {
+                    eprintln!("Failed to compress clipboard text: {}", e);
+                    return;
+                }
+            };
+
+            let (size, compressed_size) = (clipboard_text.len(), compressed.data.len());
+            if compressed_size > max_bytes {
+                notify_capture(
+                    app,
+                    &format!(
+                        "A copied text of {} was not recorded, it is still {} once compressed and the limit is {}",
+                        format_size(size),
+                        format_size(compressed_size),
+                        format_size(max_bytes)
+                    ),
+                );
+                return;
+            }
+            notify_capture(
+                app,
+                &format!(
+                    "A copied text of {} was stored compressed ({}), it is larger than the limit of its type",
+                    format_size(size),
+                    format_size(compressed_size)
+                ),
+            );
+            store
+                .insert_compressed_text(format, compressed, source, &marks, deduplicate)
+                .await
+        }
+    };
+    let (id, existed) = match inserted {
+        Ok(inserted) => inserted,
+        Err(e) => {
+            eprintln!("Failed to store clipboard text: {}", e);
+            return;
+        }
+    };
+
+    // The entry was moved to the top, remove it from its previous position
+    if existed {
+        let _ = app.emit("delete-item", id);
+    }
+
+    let max_characters = configuration.max_displayed_characters.max(0) as usize;
+    if let Ok(row) = store.preview(id, max_characters).await {
+        let _ = app.emit("new-clipboard-item", row);
+    }
+}
+
+// Function to record a new image of the clipboard, unless it is marked as not to be recorded or too large
+async fn capture_image(
+    app: &AppHandle,
+    store: &SharedHistoryStore,
+    new_image_base64: &str,
+    source: Option<&SourceApp>,
+    do_not_record: bool,
+) {
+    let configuration: AppConfig = config();
+
+    // Images are already compressed, the larger ones are skipped whatever the action
+    // Their size is checked first, so that they are never hashed nor decoded whole
+    if let Some(limit) = configuration.capture_limit("image") {
+        let max_bytes = (limit.max_size_kb as usize).saturating_mul(1024);
+        let size = new_image_base64.len() / 4 * 3;
+        if size > max_bytes {
+            let fingerprint = sampled_image_fingerprint(new_image_base64);
+            if fingerprint != std::mem::replace(&mut *LAST_IMAGE.lock().unwrap(), fingerprint) {
+                notify_capture(
+                    app,
+                    &format!(
+                        "A copied image of {} was not recorded, the limit is {}",
+                        format_size(size),
+                        format_size(max_bytes)
+                    ),
+                );
+            }
+            return;
+        }
+    }
+
+    let fingerprint = image_fingerprint(new_image_base64);
+    if fingerprint == std::mem::replace(&mut *LAST_IMAGE.lock().unwrap(), fingerprint) {
+        return;
+    }
+
+    if do_not_record {
+        return;
+    }
+
+    // Store the image in the blob store, only its metadata goes to the database
+    let marks = EntryMarks {
+        expiry_minutes: configuration.default_expiry("image", None),
+        ..EntryMarks::default()
+    };
+    let inserted = store
+        .insert_image(
+            new_image_base64,
+            source,
+            &marks,
+            configuration.deduplicate_history,
+        )
+        .await;
+    match inserted {
+        Ok((id, existed)) => {
+            // The entry was moved to the top, remove it from its previous position
+            if existed {
+                let _ = app.emit("delete-item", id);
+            }
+
+            let max_characters = configuration.max_displayed_characters.max(0) as usize;
+            if let Ok(row) = store.preview(id, max_characters).await {
+                let _ = app.emit("new-clipboard-item", row);
+            }
+        }
+        Err(e) => eprintln!("Failed to store image: {}", e),
     }
 }
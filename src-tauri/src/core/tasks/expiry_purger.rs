@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter};
+use tokio::time::sleep;
+
+use crate::core::history_store::SharedHistoryStore;
+
+// Delay between two checks of the expired entries
+const PURGE_INTERVAL: Duration = Duration::from_secs(30);
+
+// Function to delete the entries whose expiry date has passed, at startup and then periodically
+pub async fn run_expiry_purger(app: AppHandle, store: SharedHistoryStore) {
+    loop {
+        match store.purge_expired().await {
+            // Keep the open window consistent with the database
+            Ok(deleted_ids) => {
+                for id in deleted_ids {
+                    let _ = app.emit("delete-item", id);
+                }
+            }
+            Err(e) => eprintln!("Failed to delete the expired entries: {}", e),
+        }
+
+        sleep(PURGE_INTERVAL).await;
+    }
+}
@@ -1,2 +1,6 @@
+pub mod backup_scheduler;
+pub mod capture_monitor;
 pub mod clipboard_watcher;
+pub mod expiry_purger;
 pub mod hotkeys_listener;
+pub mod retention_pruner;
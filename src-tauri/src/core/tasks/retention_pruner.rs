@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter};
+use tokio::time::sleep;
+
+use crate::{
+    core::history_store::SharedHistoryStore,
+    structures::config::{config, AppConfig},
+};
+
+// Function to prune the history and the trash at startup and then periodically, according to the retention policy
+pub async fn run_retention_pruner(app: AppHandle, store: SharedHistoryStore) {
+    loop {
+        let configuration: AppConfig = config();
+
+        // The pruning runs on the storage writer, it may take a while on large databases
+        let deleted_ids = store.prune(&configuration).await.unwrap_or_else(|e| {
+            eprintln!("Failed to apply the retention policy: {}", e);
+            Vec::new()
+        });
+
+        // Keep the open window consistent with the database
+        for id in deleted_ids {
+            let _ = app.emit("delete-item", id);
+        }
+
+        // Entries of the trash are not displayed, no event is needed
+        if let Some(days) = configuration.trash_retention_days {
+            if let Err(e) = store.purge_trash(days).await {
+                eprintln!("Failed to purge the trash: {}", e);
+            }
+        }
+
+        let interval = configuration.retention_interval_minutes.max(1);
+        sleep(Duration::from_secs(interval * 60)).await;
+    }
+}
@@ -0,0 +1,392 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    Local,
+};
+
+// Grammar of the templates:
+//   template     := (text | "{{" | "}}" | placeholder)*
+//   placeholder  := "{" name (":" argument)? "}"
+// `{{` and `}}` are literal braces, the argument runs up to the closing brace and may contain ':'
+//
+// Placeholders:
+//   {date} {date:FORMAT}   current date, strftime format (default %Y-%m-%d)
+//   {time} {time:FORMAT}   current time, strftime format (default %H:%M:%S)
+//   {uuid}                 random UUID (v4), a new one for each occurrence
+//   {clipboard}            text currently in the clipboard
+//   {entry:ID}             content of another entry of the history (its own placeholders are not expanded)
+//   {input:NAME}           value asked to the user, the same for every occurrence of NAME
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+const DEFAULT_TIME_FORMAT: &str = "%H:%M:%S";
+
+// Error found in a template, `position` is the index (in characters) of the faulty placeholder or brace
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("{message} (at character {})", position + 1)]
+pub struct TemplateError {
+    pub position: usize,
+    pub message: String,
+}
+
+// Function to build an error at a position of the template
+fn error(position: usize, message: impl Into<String>) -> TemplateError {
+    TemplateError {
+        position,
+        message: message.into(),
+    }
+}
+
+// Enum to represent a placeholder, with its parsed argument
+#[derive(Debug, Clone, PartialEq)]
+enum Placeholder {
+    Date(String),
+    Time(String),
+    Uuid,
+    Clipboard,
+    Entry(i64),
+    Input(String),
+}
+
+// Enum to represent a part of a template
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Placeholder(Placeholder, usize), // Placeholder and the position of its opening brace
+}
+
+// Values of the placeholders that depend on the outside world, gathered before the expansion
+#[derive(Debug, Clone, Default)]
+pub struct TemplateValues {
+    pub clipboard: Option<String>, // Text in the clipboard, None if it doesn't hold text
+    pub entries: HashMap<i64, String>, // Content of the entries referenced by {entry:ID}
+    pub inputs: HashMap<String, String>, // Values typed by the user for {input:NAME}
+}
+
+// Struct to represent a parsed template
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+// Function to check a strftime format
+fn check_format(format: &str, position: usize) -> Result<String, TemplateError> {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(error(
+            position,
+            format!("Invalid date format \"{}\"", format),
+        ));
+    }
+    Ok(format.to_string())
+}
+
+// Function to parse the inside of a placeholder (between the braces)
+fn parse_placeholder(inner: &str, position: usize) -> Result<Placeholder, TemplateError> {
+    let (name, argument) = match inner.split_once(':') {
+        Some((name, argument)) => (name.trim(), Some(argument)),
+        None => (inner.trim(), None),
+    };
+
+    let no_argument = |placeholder: Placeholder| match argument {
+        Some(_) => Err(error(
+            position,
+            format!("{{{}}} doesn't take an argument", name),
+        )),
+        None => Ok(placeholder),
+    };
+
+    match name.to_lowercase().as_str() {
+        "date" => Ok(Placeholder::Date(check_format(
+            argument.unwrap_or(DEFAULT_DATE_FORMAT),
+            position,
+        )?)),
+        "time" => Ok(Placeholder::Time(check_format(
+            argument.unwrap_or(DEFAULT_TIME_FORMAT),
+            position,
+        )?)),
+        "uuid" => no_argument(Placeholder::Uuid),
+        "clipboard" => no_argument(Placeholder::Clipboard),
+        "entry" => argument
+            .and_then(|id| id.trim().parse::<i64>().ok())
+            .map(Placeholder::Entry)
+            .ok_or_else(|| error(position, "{entry:ID} needs the numeric ID of an entry")),
+        "input" => argument
+            .map(str::trim)
+            .filter(|input| !input.is_empty())
+            .map(|input| Placeholder::Input(input.to_string()))
+            .ok_or_else(|| error(position, "{input:NAME} needs a name")),
+        "" => Err(error(
+            position,
+            "Empty placeholder, write {{ for a literal brace",
+        )),
+        _ => Err(error(position, format!("Unknown placeholder {{{}}}", name))),
+    }
+}
+
+// Implement methods for Template
+impl Template {
+    // Function to parse a template, the first error is reported with its position
+    pub fn parse(source: &str) -> Result<Template, TemplateError> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut text = String::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            match chars[i] {
+                '{' if chars.get(i + 1) == Some(&'{') => {
+                    text.push('{');
+                    i += 2;
+                }
+                '}' if chars.get(i + 1) == Some(&'}') => {
+                    text.push('}');
+                    i += 2;
+                }
+                '}' => return Err(error(i, "Unexpected '}', write }} for a literal brace")),
+                '{' => {
+                    let start = i;
+                    let mut inner = String::new();
+                    i += 1;
+                    loop {
+                        match chars.get(i) {
+                            Some('}') => break,
+                            Some('{') => {
+                                return Err(error(i, "Unexpected '{' inside a placeholder"));
+                            }
+                            Some(c) => inner.push(*c),
+                            None => return Err(error(start, "Unclosed placeholder")),
+                        }
+                        i += 1;
+                    }
+                    i += 1;
+
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Placeholder(
+                        parse_placeholder(&inner, start)?,
+                        start,
+                    ));
+                }
+                c => {
+                    text.push(c);
+                    i += 1;
+                }
+            }
+        }
+
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Template { segments })
+    }
+
+    // Function to iterate over the placeholders of the template
+    fn placeholders(&self) -> impl Iterator<Item = &Placeholder> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Placeholder(placeholder, _) => Some(placeholder),
+            Segment::Text(_) => None,
+        })
+    }
+
+    // Function to get the names of the inputs of the template, in order of appearance and without duplicates
+    pub fn inputs(&self) -> Vec<String> {
+        let mut seen: HashSet<&str> = HashSet::new();
+        self.placeholders()
+            .filter_map(|placeholder| match placeholder {
+                Placeholder::Input(name) if seen.insert(name.as_str()) => Some(name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Function to get the inputs of the template missing from `values`
+    pub fn missing_inputs(&self, values: &HashMap<String, String>) -> Vec<String> {
+        self.inputs()
+            .into_iter()
+            .filter(|name| !values.contains_key(name))
+            .collect()
+    }
+
+    // Function to get the IDs of the entries referenced by the template
+    pub fn entry_ids(&self) -> Vec<i64> {
+        let mut ids: Vec<i64> = self
+            .placeholders()
+            .filter_map(|placeholder| match placeholder {
+                Placeholder::Entry(id) => Some(*id),
+                _ => None,
+            })
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    // Function to check if the template reads the clipboard
+    pub fn uses_clipboard(&self) -> bool {
+        self.placeholders()
+            .any(|placeholder| *placeholder == Placeholder::Clipboard)
+    }
+
+    // Function to replace the placeholders with their values
+    // The date and time are read once, so every placeholder of the template shows the same instant
+    pub fn expand(&self, values: &TemplateValues) -> Result<String, TemplateError> {
+        let now = Local::now();
+        let mut output = String::new();
+
+        for segment in &self.segments {
+            let (placeholder, position) = match segment {
+                Segment::Text(text) => {
+                    output.push_str(text);
+                    continue;
+                }
+                Segment::Placeholder(placeholder, position) => (placeholder, *position),
+            };
+
+            match placeholder {
+                Placeholder::Date(format) | Placeholder::Time(format) => {
+                    output.push_str(&now.format(format).to_string())
+                }
+                Placeholder::Uuid => output.push_str(&uuid::Uuid::new_v4().to_string()),
+                Placeholder::Clipboard => output.push_str(
+                    values
+                        .clipboard
+                        .as_deref()
+                        .ok_or_else(|| error(position, "The clipboard doesn't hold any text"))?,
+                ),
+                Placeholder::Entry(id) => output.push_str(
+                    values
+                        .entries
+                        .get(id)
+                        .ok_or_else(|| error(position, format!("Entry {} not found", id)))?,
+                ),
+                Placeholder::Input(name) => {
+                    output.push_str(values.inputs.get(name).ok_or_else(|| {
+                        error(position, format!("No value for {{input:{}}}", name))
+                    })?)
+                }
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Function to build the values of a template from the typed inputs
+    fn with_inputs(inputs: &[(&str, &str)]) -> TemplateValues {
+        TemplateValues {
+            inputs: inputs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            ..TemplateValues::default()
+        }
+    }
+
+    // Function to parse and expand a template
+    fn expand(source: &str, values: &TemplateValues) -> Result<String, TemplateError> {
+        Template::parse(source)?.expand(values)
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        let values = TemplateValues::default();
+        assert_eq!(expand("{{date}}", &values).unwrap(), "{date}");
+        assert_eq!(expand("fn f() {{ }}", &values).unwrap(), "fn f() { }");
+        assert_eq!(expand("{{{{}}", &values).unwrap(), "{{}");
+        assert_eq!(expand("no placeholder", &values).unwrap(), "no placeholder");
+    }
+
+    #[test]
+    fn adjacent_placeholders_are_expanded() {
+        let values = TemplateValues {
+            clipboard: Some("clip".to_string()),
+            ..with_inputs(&[("first", "John"), ("last", "Doe")])
+        };
+        assert_eq!(
+            expand("{input:first}{input:last}", &values).unwrap(),
+            "JohnDoe"
+        );
+        assert_eq!(expand("{{{clipboard}}}", &values).unwrap(), "{clip}");
+
+        let uuids = expand("{uuid}{uuid}", &values).unwrap();
+        assert_eq!(uuids.len(), 72);
+        assert_ne!(uuids[..36], uuids[36..]);
+    }
+
+    #[test]
+    fn nested_placeholders_are_rejected() {
+        let e = Template::parse("Hi {input:{name}}").unwrap_err();
+        assert_eq!(e.position, 10);
+        assert_eq!(e.message, "Unexpected '{' inside a placeholder");
+    }
+
+    #[test]
+    fn errors_point_at_the_faulty_placeholder() {
+        let e = Template::parse("Hello {date").unwrap_err();
+        assert_eq!(e.position, 6);
+        assert_eq!(e.message, "Unclosed placeholder");
+        assert_eq!(e.to_string(), "Unclosed placeholder (at character 7)");
+
+        let e = Template::parse("é {foo} {date}").unwrap_err();
+        assert_eq!(e.position, 2);
+        assert_eq!(e.message, "Unknown placeholder {foo}");
+
+        let e = Template::parse("a } b").unwrap_err();
+        assert_eq!(e.position, 2);
+
+        let e = Template::parse("{uuid:4}").unwrap_err();
+        assert_eq!(e.message, "{uuid} doesn't take an argument");
+
+        assert_eq!(Template::parse("x {entry:abc}").unwrap_err().position, 2);
+        assert_eq!(Template::parse("{input: }").unwrap_err().position, 0);
+        assert_eq!(Template::parse("{}").unwrap_err().position, 0);
+    }
+
+    #[test]
+    fn inputs_are_listed_once_in_order() {
+        let template = Template::parse("{input:name}, {input: city } - {input:name}").unwrap();
+        assert_eq!(template.inputs(), vec!["name", "city"]);
+
+        let values = with_inputs(&[("name", "Ada")]);
+        assert_eq!(template.missing_inputs(&values.inputs), vec!["city"]);
+        assert_eq!(
+            template.expand(&values).unwrap_err().message,
+            "No value for {input:city}"
+        );
+
+        let values = with_inputs(&[("name", "Ada"), ("city", "London")]);
+        assert!(template.missing_inputs(&values.inputs).is_empty());
+        assert_eq!(template.expand(&values).unwrap(), "Ada, London - Ada");
+    }
+
+    #[test]
+    fn referenced_entries_are_inserted_without_expansion() {
+        let template = Template::parse("{entry:2} and {entry:1}, {entry:2}").unwrap();
+        assert_eq!(template.entry_ids(), vec![1, 2]);
+
+        // An entry referencing itself or another template is inserted as is, the expansion never loops
+        let values = TemplateValues {
+            entries: HashMap::from([
+                (1, "{entry:1}".to_string()),
+                (2, "{entry:2} {uuid}".to_string()),
+            ]),
+            ..TemplateValues::default()
+        };
+        assert_eq!(
+            template.expand(&values).unwrap(),
+            "{entry:2} {uuid} and {entry:1}, {entry:2} {uuid}"
+        );
+
+        let e = Template::parse("ab {entry:3}")
+            .unwrap()
+            .expand(&values)
+            .unwrap_err();
+        assert_eq!(e.position, 3);
+        assert_eq!(e.message, "Entry 3 not found");
+    }
+}
@@ -26,25 +26,52 @@ use tauri::{
     Manager,
 };
 
+use std::sync::Arc;
+
 use tauri_plugin_opener::OpenerExt;
 
 use commands::{
-    clipboard_api::{get_clipboard_entries_ids, get_clipboard_entry, push_to_clipboard},
+    archive_api::{export_history, import_history},
+    backup_api::{list_backups, restore_backup},
+    capture_api::{get_capture_state, pause_capture, resume_capture},
+    clipboard_api::{
+        get_clipboard_entries_ids, get_clipboard_entry, list_entries, push_to_clipboard,
+    },
+    collections_api::{
+        add_tag, add_to_collection, create_collection, delete_collection, list_collections,
+        remove_from_collection, remove_tag, rename_collection,
+    },
     delete_item::{delete_all, delete_item},
+    encryption_api::rotate_encryption_key,
+    entry_details::{set_entry_expiry, set_entry_note, set_entry_template, set_entry_title},
     force_language::force_language,
     pin_item::{toggle_pin, unpin_all},
+    profiles_api::{
+        create_profile, delete_profile, list_profiles, profile_label, switch_profile,
+        ProfileMenuItem,
+    },
     resize_window::resize_window,
+    search_entries::search_entries,
     settings_api::{
         cancel_config, get_config_value, open_settings, preview_config, reset_config, save_config,
     },
     show_window::show_window,
     toggle_window::{slide_window, toggle_window},
+    trash_api::{empty_trash, list_trash, restore_item, undo_last_operation},
     url_opener::open_url
 };
 use core::{
     app_handle::{app_handle as get_app_handle, APP_HANDLE},
-    database_api::{init_db, DATABASE_CONNECTION},
-    tasks::{clipboard_watcher::watch_clipboard, hotkeys_listener::spawn_hotkey_listener},
+    capture_state::{self as capture, pause_menu_label, publish_capture_state, PauseMenuItem},
+    history_store::{SharedHistoryStore, SqliteHistoryStore},
+    paths::{active_profile, get_db_path, migrate_legacy_files},
+    profiles::ProfileStore,
+    tasks::{
+        backup_scheduler::run_backup_scheduler, capture_monitor::run_capture_monitor,
+        clipboard_watcher::watch_clipboard,
+        expiry_purger::run_expiry_purger,
+        hotkeys_listener::spawn_hotkey_listener, retention_pruner::run_retention_pruner,
+    },
 };
 use structures::config::config;
 
@@ -60,12 +87,27 @@ use commands::manage_native_clipboard::{
 /* | |___| |\  | | | |  _ < | | |  __/| |_| | || |\  | | |   */
 /* |_____|_| \_| |_| |_| \_\|_| |_|    \___/___|_| \_| |_|   */
 
+// Duration of the pause started from the tray
+const TRAY_PAUSE_MINUTES: u64 = 10;
+
 fn main() {
-    // Initialize the database connection
-    init_db();
+    // Move the files of a previous version to the current locations, before anything opens them
+    migrate_legacy_files();
 
     // Load the configuration
-    let _ = config();
+    let configuration = config();
+
+    // Open the history, nothing can work without it
+    let store = match SqliteHistoryStore::open(&get_db_path(), &configuration) {
+        Ok(store) => Arc::new(ProfileStore::new(store)),
+        Err(e) => {
+            eprintln!("Failed to initialize the database, refusing to start: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // The commands and the tasks only see the history through the HistoryStore trait
+    let history: SharedHistoryStore = store.clone();
 
     // Initialize the Tauri application with the specified configuration
     let _application = tauri::Builder::default()
@@ -75,17 +117,25 @@ fn main() {
             Some(vec![]),
         ))
         .plugin(tauri_plugin_clipboard::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_opener::init())
+        .manage(store)
+        .manage(history)
         .setup(|app| {
             let app_handle = app.handle();
             // Create a system tray icon and menu
+            let profile = MenuItem::with_id(app, "profile", profile_label(&active_profile()), false, None::<&str>)?;
+            app.manage(ProfileMenuItem(profile.clone()));
+            let pause = MenuItem::with_id(app, "pause", pause_menu_label(false), true, None::<&str>)?;
+            app.manage(PauseMenuItem(pause.clone()));
+            let pause_timed = MenuItem::with_id(app, "pause_timed", format!("⏱️ Pause for {} minutes", TRAY_PAUSE_MINUTES), true, None::<&str>)?;
             let quit = MenuItem::with_id(app, "quit", "🚪 Quit", true, None::<&str>)?;
             let prefs = MenuItem::with_id(app, "settings", "🔧 Settings", true, None::<&str>)?;
             let feature = MenuItem::with_id(app, "feature", "🚀 Request a feature", true, None::<&str>)?;
             let bug = MenuItem::with_id(app, "bug", "🐞 Report a bug", true, None::<&str>)?;
-            let tray_menu = Menu::with_items(app, &[&prefs, &feature, &bug, &quit])?;
-            let _tray = TrayIconBuilder::new()
+            let tray_menu = Menu::with_items(app, &[&profile, &pause, &pause_timed, &prefs, &feature, &bug, &quit])?;
+            let _tray = TrayIconBuilder::with_id("main")
                 .title("TactiClip")
                 .tooltip("TactiClip")
                 .show_menu_on_left_click(true)
@@ -98,6 +148,19 @@ fn main() {
 
                         parent_app.exit(0);
                     }
+                    "pause" => {
+                        // Toggle the pause started from the tray or a command
+                        if capture::is_capture_paused() {
+                            capture::resume_capture();
+                        } else {
+                            capture::pause_capture(None);
+                        }
+                        publish_capture_state(parent_app);
+                    }
+                    "pause_timed" => {
+                        capture::pause_capture(Some(TRAY_PAUSE_MINUTES));
+                        publish_capture_state(parent_app);
+                    }
                     "settings" => {
                         tauri::async_runtime::spawn(async move {
                             // Open the settings window
@@ -124,7 +187,6 @@ fn main() {
             let handle = app_handle.clone();
 
             APP_HANDLE.set(handle.to_owned()).unwrap();
-            let db_conn = DATABASE_CONNECTION.get().expect("DB non initialisée");
 
             // Spwawn the window after 500 ms to hide the webview loading (white screen)
             tauri::async_runtime::spawn(async move {
@@ -134,9 +196,36 @@ fn main() {
                 window.show().unwrap();
             });
 
+            // Spawn the retention pruner (runs at startup, then periodically)
+            let pruner_handle = handle.clone();
+            let pruner_store = app.state::<SharedHistoryStore>().inner().clone();
+            tauri::async_runtime::spawn(async move {
+                run_retention_pruner(pruner_handle, pruner_store).await;
+            });
+
+            // Spawn the monitor of the capture pause (end of the timed pauses, screen lock)
+            let monitor_handle = handle.clone();
+            tauri::async_runtime::spawn(async move {
+                run_capture_monitor(monitor_handle).await;
+            });
+
+            // Spawn the purger of the expired entries (secrets copied with a short lifetime)
+            let purger_handle = handle.clone();
+            let purger_store = app.state::<SharedHistoryStore>().inner().clone();
+            tauri::async_runtime::spawn(async move {
+                run_expiry_purger(purger_handle, purger_store).await;
+            });
+
+            // Spawn the backup scheduler (backs up at startup if the last backup is too old)
+            let backup_store = app.state::<Arc<ProfileStore>>().inner().clone();
+            tauri::async_runtime::spawn(async move {
+                run_backup_scheduler(backup_store).await;
+            });
+
             // Spawn the clipboard watcher
+            let watcher_store = app.state::<SharedHistoryStore>().inner().clone();
             tauri::async_runtime::spawn(async move {
-                watch_clipboard(handle, db_conn).await;
+                watch_clipboard(handle, watcher_store).await;
             });
 
             // Spawn the hotkey listener
@@ -149,6 +238,8 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             get_clipboard_entries_ids,
             get_clipboard_entry,
+            list_entries,
+            search_entries,
             show_window,
             resize_window,
             toggle_window,
@@ -160,11 +251,39 @@ fn main() {
             delete_all,
             open_settings,
             force_language,
+            set_entry_title,
+            set_entry_note,
+            set_entry_template,
+            set_entry_expiry,
             reset_config,
             save_config,
             preview_config,
             cancel_config,
             open_url,
+            rotate_encryption_key,
+            list_collections,
+            create_collection,
+            rename_collection,
+            delete_collection,
+            add_to_collection,
+            remove_from_collection,
+            add_tag,
+            remove_tag,
+            list_trash,
+            restore_item,
+            empty_trash,
+            undo_last_operation,
+            export_history,
+            import_history,
+            list_backups,
+            restore_backup,
+            list_profiles,
+            create_profile,
+            delete_profile,
+            switch_profile,
+            pause_capture,
+            resume_capture,
+            get_capture_state,
             #[cfg(windows)]
             disable_windows_clipboard_history,
             #[cfg(windows)]
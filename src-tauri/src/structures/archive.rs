@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+use crate::structures::source_app::SourceApp;
+
+// Name of the archive format, written in the manifest
+pub const ARCHIVE_FORMAT: &str = "tacticlip-history";
+
+// Version of the archive format, bumped on incompatible changes
+pub const ARCHIVE_VERSION: u32 = 2;
+
+// Struct to represent the manifest of an archive (`manifest.json`)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ArchiveManifest {
+    pub format: String,      // Always ARCHIVE_FORMAT
+    pub version: u32,        // Version of the format used to write the archive
+    pub exported_at: String, // Date of the export (UTC, YYYY-MM-DD HH:MM:SS)
+    pub entry_count: usize,  // Number of lines of `entries.ndjson`
+}
+
+// Struct to represent an entry of an archive (one line of `entries.ndjson`)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ArchiveEntry {
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    #[serde(default)]
+    pub content: String, // Empty for images
+    #[serde(default)]
+    pub image: Option<String>, // Path of the PNG file in the archive, for images
+    pub content_hash: String, // SHA-256 of the content (of the PNG file for images)
+    #[serde(default)]
+    pub added_at: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub forced_language: Option<String>,
+    #[serde(default)]
+    pub paste_count: i64,
+    #[serde(default)]
+    pub last_used_at: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub collections: Vec<String>, // Names of the collections, created on import if needed
+    #[serde(default)]
+    pub source: Option<SourceApp>, // Application the entry was copied from
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub is_template: bool,
+    #[serde(default)]
+    pub sensitive: Option<String>, // Category of the secret held by the content
+    #[serde(default)]
+    pub masked_content: Option<String>, // Content shown in the previews instead of the secret
+    #[serde(default)]
+    pub expires_at: Option<String>, // Date after which the entry is deleted (UTC)
+}
+
+// Enum to represent how an imported entry is handled when the history already holds the same content
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportConflict {
+    #[default]
+    Skip, // Keep the existing entry untouched
+    Overwrite, // Replace the metadata of the existing entry with the imported one
+    Merge,     // Combine both: pinned if either is, union of tags and collections, latest dates
+}
+
+// Struct to represent the outcome of an import
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,    // New entries added to the history
+    pub skipped: usize,     // Duplicates left untouched
+    pub overwritten: usize, // Duplicates replaced by the imported entry
+    pub merged: usize,      // Duplicates merged with the imported entry
+    pub failed: usize,      // Entries that couldn't be read (invalid line or missing image)
+}
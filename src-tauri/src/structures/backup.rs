@@ -0,0 +1,9 @@
+use serde::Serialize;
+
+// Struct to represent a backup of the database
+#[derive(Serialize, Clone, Debug)]
+pub struct Backup {
+    pub id: String,         // Name of the backup directory (YYYYMMDD-HHMMSS-mmm, UTC)
+    pub created_at: String, // Date of the backup (UTC, YYYY-MM-DD HH:MM:SS)
+    pub size: u64,          // Size of the database file in bytes (images excluded)
+}
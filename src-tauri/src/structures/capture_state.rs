@@ -0,0 +1,18 @@
+use serde::Serialize;
+
+// Enum to represent why the capture is paused
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseReason {
+    Manual,       // Paused until resumed
+    Timer,        // Paused for a given duration
+    ScreenLocked, // Paused while the screen is locked
+}
+
+// Struct to represent the state of the capture, shown in the tray and sent to the window
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CaptureState {
+    pub paused: bool,
+    pub reason: Option<PauseReason>,  // None while capturing
+    pub paused_until: Option<String>, // End of a timed pause (UTC, YYYY-MM-DD HH:MM:SS)
+}
@@ -1,5 +1,7 @@
 use serde::Serialize;
 
+use crate::structures::source_app::SourceApp;
+
 // Struct to represent a clipboard entry
 #[derive(Serialize, Clone, Debug)]
 pub struct ClipboardEntry {
@@ -9,4 +11,16 @@ pub struct ClipboardEntry {
     pub added_at: String,
     pub pinned: bool,
     pub forced_language: Option<String>,
+    pub paste_count: i64, // Number of times the entry was pushed back to the clipboard
+    pub last_used_at: Option<String>, // Last time the entry was pushed back to the clipboard
+    pub tags: Vec<String>,
+    pub collections: Vec<i64>, // IDs of the collections the entry belongs to
+    pub source: Option<SourceApp>, // Application the entry was last copied from, when known
+    pub title: Option<String>, // Title given by the user, shown instead of the content
+    pub note: Option<String>,  // Note written by the user
+    pub is_template: bool, // Placeholders are expanded when the entry is pushed to the clipboard
+    pub sensitive: Option<String>, // Category of the secret found in the entry (password, api_key...)
+    pub expires_at: Option<String>, // Date after which the entry is deleted
+    #[serde(skip)]
+    pub masked_content: Option<String>, // Content with the secrets masked, shown in the previews
 }
@@ -0,0 +1,10 @@
+use serde::Serialize;
+
+// Struct to represent a named collection of clipboard entries
+#[derive(Serialize, Clone, Debug)]
+pub struct Collection {
+    pub id: i64,
+    pub name: String,
+    pub created_at: String,
+    pub entry_count: i64, // Number of entries in the collection
+}
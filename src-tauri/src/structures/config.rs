@@ -1,13 +1,96 @@
-use std::{path::PathBuf, sync::RwLock};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{PoisonError, RwLock},
+};
 
-use directories::BaseDirs;
 use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json;
 
+use crate::core::paths::get_config_path;
+
 // Global singleton configuration object
 static CONFIG: Lazy<RwLock<Option<AppConfig>>> = Lazy::new(|| RwLock::new(None));
 
+// Struct to represent retention limits, all of them are optional (None means no limit)
+#[derive(Serialize, Clone, Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct RetentionLimits {
+    pub max_entries: Option<i64>,  // Maximum number of unpinned entries kept
+    pub max_age_days: Option<i64>, // Maximum age of an unpinned entry in days
+    pub max_size_mb: Option<i64>,  // Maximum size of the stored content in MB
+}
+
+// Enum to represent what happens to a copied content larger than the limit of its type
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OversizedAction {
+    Skip,     // Not recorded
+    Truncate, // Texts only, the beginning is recorded with a marker at the end (images are skipped)
+    Compress, // Texts only, recorded compressed if that fits in the limit (images are skipped)
+}
+
+// Struct to represent the size limit of the copied contents of a type
+#[derive(Serialize, Clone, Debug, Deserialize)]
+pub struct CaptureLimit {
+    pub max_size_kb: u64,        // Maximum size of the content in KB
+    pub action: OversizedAction, // What happens to larger contents
+}
+
+// Function to get the default size limits: 1 MB for the texts, 20 MB for the images
+fn default_capture_limits() -> HashMap<String, CaptureLimit> {
+    HashMap::from([
+        (
+            "text".to_string(),
+            CaptureLimit {
+                max_size_kb: 1024,
+                action: OversizedAction::Truncate,
+            },
+        ),
+        (
+            "image".to_string(),
+            CaptureLimit {
+                max_size_kb: 20 * 1024,
+                action: OversizedAction::Skip,
+            },
+        ),
+    ])
+}
+
+// Struct to represent a rule giving a lifetime to the copied texts matching a pattern
+#[derive(Serialize, Clone, Debug, Deserialize)]
+pub struct ExpiryRule {
+    pub pattern: String, // Regular expression matched against the copied text
+    pub minutes: i64,    // Lifetime of the matching entries, 0 keeps them forever
+    #[serde(skip)]
+    regex: Option<Regex>, // Compiled pattern, set when the configuration is loaded or updated
+}
+
+// Enum to represent what happens to a copied text holding a secret (password, API key, card number...)
+// Ordered from the mildest to the strictest, the strictest action of the secrets found applies
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SensitiveAction {
+    Store,  // Stored as any other entry
+    Mask,   // Stored, the secret is masked in the previews
+    Expire, // Stored masked and deleted after `sensitive_expiry_minutes`
+    Skip,   // Never stored
+}
+
+// Function to get the default action for each category of secrets
+fn default_sensitive_actions() -> HashMap<String, SensitiveAction> {
+    HashMap::from([
+        ("password".to_string(), SensitiveAction::Expire),
+        ("api_key".to_string(), SensitiveAction::Expire),
+        ("jwt".to_string(), SensitiveAction::Expire),
+        ("credit_card".to_string(), SensitiveAction::Mask),
+        ("private_key".to_string(), SensitiveAction::Skip),
+        ("secret".to_string(), SensitiveAction::Mask),
+    ])
+}
+
 // Struct to represent the configuration of the application
 #[derive(Serialize, Clone, Debug, Deserialize)]
 #[serde(default = "AppConfig::default")]
@@ -29,11 +112,58 @@ pub struct AppConfig {
     pub auto_hide_on_copy: bool, // Flag to indicate if the window should auto-hide on copy
     pub auto_paste_on_copy: bool, // Flag to indicate if the clipboard should be auto-pasted on copy
     pub max_displayed_characters: i32, // Maximum number of characters to display in the window
+    pub deduplicate_history: bool, // Flag to indicate if copying known content should move the existing entry to the top
+    pub capture_limits: HashMap<String, CaptureLimit>, // Size limit per type, "text" also applies to urls, emails and colors
 
     // Scroll-specific fields
     pub reset_scroll_on_show: bool, // Flag to indicate if the scroll should be reset on show
     pub scroll_factor: f64,         // Factor to scroll the window
     pub smooth_scroll: bool,        // Flag to indicate if the scroll should be smooth
+
+    // Retention-specific fields (pinned entries are never pruned)
+    pub retention_max_entries: Option<i64>, // Maximum number of unpinned entries kept in the history
+    pub retention_max_age_days: Option<i64>, // Maximum age of an unpinned entry in days
+    pub retention_max_database_size_mb: Option<i64>, // Maximum size of the history content in MB
+    pub retention_per_type: HashMap<String, RetentionLimits>, // Limits applied to a single type of entry
+    pub retention_interval_minutes: u64,                      // Delay between two pruning runs
+    pub trash_retention_days: Option<i64>, // Days before an entry of the trash is deleted for good (None keeps it)
+
+    // Expiry-specific fields (pinned entries never expire)
+    pub expiry_rules: Vec<ExpiryRule>, // Lifetimes given to the copied texts matching a pattern, the first match wins
+    pub expiry_per_type: HashMap<String, i64>, // Lifetime in minutes of the new entries of a type, when no rule matches
+
+    // Sensitive content-specific fields
+    pub sensitive_detection_enabled: bool, // Flag to indicate if copied texts are checked for secrets
+    pub sensitive_actions: HashMap<String, SensitiveAction>, // Action per category (password, api_key, jwt, credit_card, private_key, secret)
+    pub sensitive_expiry_minutes: i64, // Lifetime of the entries holding a secret with the "expire" action
+    pub pause_on_screen_lock: bool, // Flag to indicate if the capture is paused while the screen is locked
+    pub ignored_clipboard_hints: Vec<String>, // Clipboard formats ("NAME" or "NAME=VALUE") marking a copy that must not be recorded
+
+    // Backup-specific fields
+    pub backup_enabled: bool, // Flag to indicate if the database is backed up periodically
+    pub backup_interval_hours: u64, // Delay between two backups
+    pub backup_keep_daily: usize, // Number of days for which the latest backup is kept
+    pub backup_keep_weekly: usize, // Number of weeks for which the latest backup is kept
+
+    // Security-specific fields
+    pub encryption_enabled: bool, // Flag to indicate if the history is encrypted at rest
+    pub encryption_key_source: String, // Origin of the key: "key_file" or "passphrase" (TACTICLIP_PASSPHRASE)
+}
+
+// Function to get the clipboard formats through which the password managers ask not to record their copies
+// KDE (KeePassXC, KWallet...), macOS (nspasteboard.org types) and Windows (clipboard history and monitors)
+fn default_ignored_clipboard_hints() -> Vec<String> {
+    [
+        "x-kde-passwordManagerHint=secret",
+        "org.nspasteboard.ConcealedType",
+        "org.nspasteboard.TransientType",
+        "ExcludeClipboardContentFromMonitorProcessing",
+        "CanIncludeInClipboardHistory=0",
+        "Clipboard Viewer Ignore",
+    ]
+    .iter()
+    .map(|hint| hint.to_string())
+    .collect()
 }
 
 // Implement the Default trait for AppConfig
@@ -57,21 +187,107 @@ impl Default for AppConfig {
             auto_hide_on_copy: true,
             auto_paste_on_copy: true,
             max_displayed_characters: 250,
+            deduplicate_history: true,
+            capture_limits: default_capture_limits(),
 
             // Scroll-specific fields
             reset_scroll_on_show: true,
             scroll_factor: 1.0,
             smooth_scroll: false,
+
+            // Retention-specific fields
+            retention_max_entries: None,
+            retention_max_age_days: None,
+            retention_max_database_size_mb: None,
+            retention_per_type: HashMap::new(),
+            retention_interval_minutes: 60,
+            trash_retention_days: Some(30),
+
+            // Expiry-specific fields
+            expiry_rules: Vec::new(),
+            expiry_per_type: HashMap::new(),
+
+            // Sensitive content-specific fields
+            sensitive_detection_enabled: true,
+            sensitive_actions: default_sensitive_actions(),
+            sensitive_expiry_minutes: 10,
+            pause_on_screen_lock: false,
+            ignored_clipboard_hints: default_ignored_clipboard_hints(),
+
+            // Backup-specific fields
+            backup_enabled: true,
+            backup_interval_hours: 24,
+            backup_keep_daily: 7,
+            backup_keep_weekly: 4,
+
+            // Security-specific fields
+            encryption_enabled: true,
+            encryption_key_source: "key_file".to_string(),
         }
     }
 }
 
 // Implement methods for AppConfig
 impl AppConfig {
+    // Function to get the action applied to a category of secrets, the default one when it isn't configured
+    pub fn sensitive_action(&self, category: &str) -> SensitiveAction {
+        self.sensitive_actions
+            .get(category)
+            .copied()
+            .or_else(|| default_sensitive_actions().get(category).copied())
+            .unwrap_or(SensitiveAction::Mask)
+    }
+
+    // Function to get the size limit of a type of entry, the limit of "text" applies to the other textual types
+    pub fn capture_limit(&self, entry_type: &str) -> Option<&CaptureLimit> {
+        self.capture_limits
+            .get(entry_type)
+            .or_else(|| match entry_type {
+                "image" => None,
+                _ => self.capture_limits.get("text"),
+            })
+    }
+
+    // Function to get the lifetime in minutes of a new entry, from the rules matching its text then from its type
+    // None keeps the entry forever, rules whose pattern is invalid never match
+    pub fn default_expiry(&self, entry_type: &str, text: Option<&str>) -> Option<i64> {
+        let by_rule = text.and_then(|text| {
+            self.expiry_rules.iter().find(|rule| {
+                rule.regex
+                    .as_ref()
+                    .is_some_and(|regex| regex.is_match(text))
+            })
+        });
+        by_rule
+            .map(|rule| rule.minutes)
+            .or_else(|| self.expiry_per_type.get(entry_type).copied())
+            .filter(|minutes| *minutes > 0)
+    }
+
+    // Function to compile the patterns of the expiry rules, returns the invalid ones (which are left uncompiled)
+    fn compile_expiry_rules(&mut self) -> Result<(), String> {
+        let mut invalid = Vec::new();
+        for rule in &mut self.expiry_rules {
+            match Regex::new(&rule.pattern) {
+                Ok(regex) => rule.regex = Some(regex),
+                Err(e) => invalid.push(format!("Invalid expiry rule {:?}: {}", rule.pattern, e)),
+            }
+        }
+        match invalid.is_empty() {
+            true => Ok(()),
+            false => Err(invalid.join("\n")),
+        }
+    }
+
     // Function to load the configuration from a JSON file
     pub fn load_from_file(file_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
         let config_data = std::fs::read_to_string(file_path)?;
-        let config: AppConfig = serde_json::from_str(&config_data)?;
+        let mut config: AppConfig = serde_json::from_str(&config_data)?;
+
+        // The file may have been edited by hand, the invalid rules are kept but never match
+        if let Err(e) = config.compile_expiry_rules() {
+            eprintln!("{}", e);
+        }
 
         // Write the config to file to ensure all fields are present
         let _ = config.save_to_file(file_path);
@@ -81,11 +297,12 @@ impl AppConfig {
 
     // Function ot update the configuration from a JSON string
     pub fn update_from_json(&mut self, json_str: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let new_config: AppConfig = serde_json::from_str(json_str)?;
+        let mut new_config: AppConfig = serde_json::from_str(json_str)?;
+        new_config.compile_expiry_rules()?;
         *self = new_config;
 
         // Push the new singleton config to the global static variable
-        let mut config_lock = CONFIG.write().unwrap();
+        let mut config_lock = CONFIG.write().unwrap_or_else(PoisonError::into_inner);
         *config_lock = Some(self.clone());
         Ok(())
     }
@@ -93,58 +310,53 @@ impl AppConfig {
     // Function to save the configuration to a JSON file
     pub fn save_to_file(&self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
         let config_data = serde_json::to_string_pretty(self)?;
+        if let Some(parent) = Path::new(file_path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
         std::fs::write(file_path, config_data)?;
         Ok(())
     }
 }
 
-// Function to get the configuration path based on the operating system
-pub fn get_config_path() -> PathBuf {
-    let base_dirs = BaseDirs::new().expect("Unable to access directories");
-
-    // Détermine l'emplacement basé sur le système d'exploitation
-    if cfg!(target_os = "windows") {
-        // Sur Windows, utiliser AppData
-        base_dirs
-            .data_local_dir()
-            .join("PetchouSoftware")
-            .join("TactiClip")
-            .join("config.json")
-    } else {
-        // Sur macOS et Linux, utiliser .local
-        base_dirs
-            .home_dir()
-            .join(".local")
-            .join("PetchouSoftware")
-            .join("TactiClip")
-            .join("config.json")
-    }
-}
-
 // Function to get the configuration, loading it from a file if necessary
+// The defaults apply when the file can't be read, the file itself is left as is
 pub fn config() -> AppConfig {
-    let mut config_lock = CONFIG.write().unwrap();
+    let mut config_lock = CONFIG.write().unwrap_or_else(PoisonError::into_inner);
     if let Some(ref config) = *config_lock {
         // If config is already loaded, return it
         return config.clone();
     }
 
     // Path to the config file
-    let config_path = get_config_path(); // Store in a variable
+    let config_path = get_config_path();
+    let file_path = config_path.to_string_lossy();
 
     // Check if config file exists, if not create it with default values
     if !config_path.exists() {
-        let default_config = AppConfig::default();
-        default_config
-            .save_to_file(config_path.to_str().unwrap())
-            .unwrap();
+        if let Err(e) = AppConfig::default().save_to_file(&file_path) {
+            eprintln!(
+                "Failed to write the default configuration to {}: {}",
+                file_path, e
+            );
+        }
     }
 
     // Load config from the file
-    let loaded_config = AppConfig::load_from_file(config_path.to_str().unwrap()).unwrap();
+    let loaded_config = AppConfig::load_from_file(&file_path).unwrap_or_else(|e| {
+        eprintln!(
+            "Failed to load the configuration from {}, using the defaults: {}",
+            file_path, e
+        );
+        AppConfig::default()
+    });
 
     // Save the loaded config to the global static variable
     *config_lock = Some(loaded_config.clone());
 
     loaded_config
 }
+
+// Function to forget the loaded configuration, the next call to `config` reads the file of the active profile
+pub fn unload_config() {
+    *CONFIG.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
@@ -0,0 +1,83 @@
+use serde::Deserialize;
+
+// Struct to represent the filters that can be applied when looking for entries
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct EntryFilters {
+    pub entry_type: Option<String>,   // Type of the entry (text, url, email, color, image)
+    pub pinned: Option<bool>,         // Only pinned (true) or unpinned (false) entries
+    pub template: Option<bool>,       // Only templates (true) or regular entries (false)
+    pub language: Option<String>,     // Language forced on the entry
+    pub added_after: Option<String>,  // Lower bound of the added_at date (inclusive)
+    pub added_before: Option<String>, // Upper bound of the added_at date (inclusive)
+    pub collection: Option<String>,   // Name of a collection the entry belongs to
+    pub tag: Option<String>,          // Tag of the entry
+    pub app: Option<String>,          // Part of the window class or process name of the source application
+    pub window: Option<String>,       // Part of the title of the source window
+    pub trashed: bool,                // Entries in the trash instead of the history
+}
+
+// Implement methods for EntryFilters
+impl EntryFilters {
+    // Function to extract the filters written in a search query
+    // (`type:url pinned:true lang:rust after:2024-01-01 before:2024-12-31 collection:snippets tag:sql app:firefox`)
+    // Returns the remaining free text and the filters found
+    pub fn from_query(query: &str) -> (String, EntryFilters) {
+        let mut filters = EntryFilters::default();
+        let mut terms: Vec<&str> = Vec::new();
+
+        for token in query.split_whitespace() {
+            let Some((key, value)) = token.split_once(':') else {
+                terms.push(token);
+                continue;
+            };
+
+            if value.is_empty() {
+                terms.push(token);
+                continue;
+            }
+
+            match key.to_lowercase().as_str() {
+                "type" => filters.entry_type = Some(value.to_lowercase()),
+                "pinned" => match value.to_lowercase().as_str() {
+                    "true" | "yes" | "1" => filters.pinned = Some(true),
+                    "false" | "no" | "0" => filters.pinned = Some(false),
+                    _ => terms.push(token),
+                },
+                "template" => match value.to_lowercase().as_str() {
+                    "true" | "yes" | "1" => filters.template = Some(true),
+                    "false" | "no" | "0" => filters.template = Some(false),
+                    _ => terms.push(token),
+                },
+                "lang" | "language" => filters.language = Some(value.to_lowercase()),
+                "after" | "since" => filters.added_after = Some(value.to_string()),
+                "before" | "until" => filters.added_before = Some(value.to_string()),
+                "collection" | "in" => filters.collection = Some(value.to_string()),
+                "tag" => filters.tag = Some(value.trim_start_matches('#').to_lowercase()),
+                "app" => filters.app = Some(value.to_lowercase()),
+                "window" | "title" => filters.window = Some(value.to_lowercase()),
+                // Not a known filter (e.g. an URL), keep it as a search term
+                _ => terms.push(token),
+            }
+        }
+
+        (terms.join(" "), filters)
+    }
+
+    // Function to merge two sets of filters, the values of `other` taking precedence
+    pub fn merge(self, other: EntryFilters) -> EntryFilters {
+        EntryFilters {
+            entry_type: other.entry_type.or(self.entry_type),
+            pinned: other.pinned.or(self.pinned),
+            template: other.template.or(self.template),
+            language: other.language.or(self.language),
+            added_after: other.added_after.or(self.added_after),
+            added_before: other.added_before.or(self.added_before),
+            collection: other.collection.or(self.collection),
+            tag: other.tag.or(self.tag),
+            app: other.app.or(self.app),
+            window: other.window.or(self.window),
+            trashed: other.trashed || self.trashed,
+        }
+    }
+}
@@ -0,0 +1,7 @@
+// Struct to represent what the watcher found out about a content, stored along with the entry
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EntryMarks {
+    pub sensitive: Option<String>, // Category of the secret held by the content
+    pub masked_content: Option<String>, // Content shown in the previews instead of the secret
+    pub expiry_minutes: Option<i64>, // Lifetime of the entry in minutes
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+use crate::structures::clipboard_entry::ClipboardEntry;
+
+// Enum to represent the available orders of the history (pinned entries always come first)
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntrySort {
+    #[default]
+    Date, // Most recent first
+    Type,     // Alphabetical order of the type
+    Size,     // Largest first
+    Usage,    // Most pasted first
+    Frecency, // Most frequently and recently used first
+}
+
+// Struct to represent the position of the last entry of a page, used to fetch the next one
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ListCursor {
+    pub pinned: bool,
+    pub key: serde_json::Value, // Value of the sort key of the last entry
+    pub id: i64,
+}
+
+// Struct to represent a page of the history
+#[derive(Serialize, Clone, Debug)]
+pub struct EntryPage {
+    pub entries: Vec<ClipboardEntry>, // Previews of the entries, ready to render
+    pub next_cursor: Option<String>,  // Cursor of the next page, None if this is the last one
+    pub total: i64,                   // Number of entries matching the filters
+}
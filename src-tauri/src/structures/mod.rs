@@ -1,2 +1,13 @@
+pub mod archive;
+pub mod backup;
+pub mod capture_state;
 pub mod clipboard_entry;
+pub mod collection;
 pub mod config;
+pub mod entry_filters;
+pub mod entry_marks;
+pub mod entry_page;
+pub mod operation;
+pub mod profile;
+pub mod search_result;
+pub mod source_app;
@@ -0,0 +1,40 @@
+use serde::Serialize;
+
+// Enum to represent the destructive operations that can be undone
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Delete,    // An entry was moved to the trash
+    DeleteAll, // The whole history was moved to the trash
+    UnpinAll,  // All entries were unpinned
+}
+
+impl OperationKind {
+    // Function to get the name of the operation, as stored in the journal
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperationKind::Delete => "delete",
+            OperationKind::DeleteAll => "delete_all",
+            OperationKind::UnpinAll => "unpin_all",
+        }
+    }
+
+    // Function to get an operation from its name in the journal
+    pub fn from_name(name: &str) -> Option<OperationKind> {
+        match name {
+            "delete" => Some(OperationKind::Delete),
+            "delete_all" => Some(OperationKind::DeleteAll),
+            "unpin_all" => Some(OperationKind::UnpinAll),
+            _ => None,
+        }
+    }
+}
+
+// Struct to represent an operation of the journal
+#[derive(Serialize, Clone, Debug)]
+pub struct Operation {
+    pub id: i64,
+    pub kind: OperationKind,
+    pub performed_at: String,
+    pub entry_ids: Vec<i64>, // Entries affected by the operation
+}
@@ -0,0 +1,8 @@
+use serde::Serialize;
+
+// Struct to represent a profile, with its own history and configuration
+#[derive(Serialize, Clone, Debug)]
+pub struct Profile {
+    pub name: String,
+    pub active: bool, // Whether the history and configuration of the profile are in use
+}
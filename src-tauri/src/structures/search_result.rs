@@ -0,0 +1,16 @@
+use serde::Serialize;
+
+// Struct to represent a part of a search snippet, highlighted if it matches the query
+#[derive(Serialize, Clone, Debug)]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlighted: bool,
+}
+
+// Struct to represent a search result
+#[derive(Serialize, Clone, Debug)]
+pub struct SearchResult {
+    pub id: i64,
+    pub rank: f64, // Relevance of the result (lower is better, as returned by bm25)
+    pub snippet: Vec<SnippetPart>,
+}
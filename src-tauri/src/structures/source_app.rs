@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+// Struct to represent the application an entry was copied from
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SourceApp {
+    pub class: Option<String>,   // Window class (e.g. firefox)
+    pub title: Option<String>,   // Title of the window when the content was copied
+    pub process: Option<String>, // Name of the process owning the window
+}
+
+// Implement methods for SourceApp
+impl SourceApp {
+    // Function to check if nothing is known about the application
+    pub fn is_empty(&self) -> bool {
+        self.class.is_none() && self.title.is_none() && self.process.is_none()
+    }
+}
@@ -2,6 +2,7 @@ import { component$ } from "@builder.io/qwik";
 import { IconHover } from "./Icon";
 import { emit } from "@tauri-apps/api/event";
 import { invoke } from "@tauri-apps/api/core";
+import { showError } from "./errors";
 
 export interface ActionRowProps {
     layout: string;
@@ -16,7 +17,7 @@ export const ActionRow = component$<ActionRowProps>(({ layout }) => {
                 onClick$={async (e) => {
                     e.stopPropagation();
 
-                    await invoke("delete_all", {});
+                    await invoke("delete_all", {}).catch(showError);
                 }}
             >
                 <IconHover regular="trash-can" solid="trash-can" class="trash-button" />
@@ -25,13 +26,27 @@ export const ActionRow = component$<ActionRowProps>(({ layout }) => {
                 async (e) => {
                     e.stopPropagation();
 
-                    const res = await invoke("unpin_all", {});
+                    const res = await invoke("unpin_all", {}).catch((error) => {
+                        showError(error);
+                        return false;
+                    });
                     if (res) {
                         emit("unpin-all");
                     }
                 }}>
                 <IconHover regular="star" solid="star" class="trash-button" />
             </button>
+            <button
+                class="action-row-button"
+                title="Undo the last deletion or unpin"
+                onClick$={async (e) => {
+                    e.stopPropagation();
+
+                    await invoke("undo_last_operation", {}).catch(showError);
+                }}
+            >
+                <IconHover regular="circle-left" solid="rotate-left" class="trash-button" />
+            </button>
             <button class="action-row-button" onClick$={async () => {
                 invoke("open_settings", {});
             }}>
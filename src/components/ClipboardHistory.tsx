@@ -4,6 +4,7 @@ import { listen } from '@tauri-apps/api/event';
 
 import { ClipboardItem } from './ClipboardItem';
 import { ActionRow } from './ActionRow';
+import { showError, type AppError } from './errors';
 
 type ClipboardEntry = {
   id: number;
@@ -12,12 +13,29 @@ type ClipboardEntry = {
   added_at: string;
   pinned: boolean;
   forced_language: string;
+  paste_count: number;
+  last_used_at: string | null;
+  tags: string[];
+  collections: number[];
+  source: { class: string | null; title: string | null; process: string | null } | null;
+  title: string | null;
+  note: string | null;
+  is_template: boolean;
+  sensitive: string | null;
+  expires_at: string | null;
+};
+
+type CaptureState = {
+  paused: boolean;
+  reason: "manual" | "timer" | "screen_locked" | null;
+  paused_until: string | null;
 };
 
 export default component$(() => {
   const loading = useSignal(true);
   const clipboardData = useSignal<any[]>([]);
   const progress = useSignal(0);
+  const captureState = useSignal<CaptureState | null>(null);
 
   const listRef = useSignal<HTMLElement>();
 
@@ -54,8 +72,15 @@ export default component$(() => {
 
     // Load the clipboard entries one by one and update the progress
     for (let i = 0; i < total; i++) {
-      const entry = await invoke('get_clipboard_entry', { id: ids[i] });
-      clipboardData.value.push(entry);
+      try {
+        const entry = await invoke('get_clipboard_entry', { id: ids[i] });
+        clipboardData.value.push(entry);
+      } catch (error) {
+        // The entry may have been deleted in the meantime, skip it silently
+        if ((error as AppError)?.kind !== 'not_found') {
+          showError(error);
+        }
+      }
       progress.value = Math.floor(((i + 1) / total) * 100);
     }
 
@@ -115,6 +140,12 @@ export default component$(() => {
       console.log("Clipboard IDs:", clipboardData.value.map((item) => item.id));
     });
 
+    // Show whether the clipboard is being recorded (paused from the tray, a command or while the screen is locked)
+    captureState.value = await invoke<CaptureState>("get_capture_state");
+    listen<CaptureState>("capture-state", (event) => {
+      captureState.value = event.payload;
+    });
+
     listen("delete-all-items", () => {
       console.log("Deleting all items");
       
@@ -194,6 +225,13 @@ export default component$(() => {
       ) : (
         <div class={"clipboard-history-container clipboard-history-container-" + layout.value + " scroll-" + positon.value}>
           <ActionRow layout={layout.value}/>
+          {captureState.value?.paused && (
+            <div class="capture-paused">
+              {captureState.value.paused_until
+                ? "⏸️ Capture paused until " + new Date(captureState.value.paused_until.replace(" ", "T") + "Z").toLocaleTimeString()
+                : "⏸️ Capture paused"}
+            </div>
+          )}
           <ul class={"clipboard-history-list clipboard-history-list-"+ layout.value} ref={listRef}>
             {clipboardData.value.map((entry) => (
               <ClipboardItem orientation={layout.value} entry={entry} key={entry.id}/>
@@ -2,6 +2,7 @@ import { component$, useSignal, useVisibleTask$ } from "@builder.io/qwik";
 import { invoke } from "@tauri-apps/api/core";
 
 import { IconHover } from "./Icon";
+import { AppError, showError } from "./errors";
 import { listen } from "@tauri-apps/api/event";
 
 import hljs from 'highlight.js';
@@ -16,6 +17,16 @@ export interface ClipboardItemProps {
     added_at: string;
     pinned: boolean;
     forced_language: string | null;
+    paste_count: number;
+    last_used_at: string | null;
+    tags: string[];
+    collections: number[];
+    source: { class: string | null; title: string | null; process: string | null } | null;
+    title: string | null;
+    note: string | null;
+    is_template: boolean;
+    sensitive: string | null;
+    expires_at: string | null;
   };
 }
 
@@ -50,10 +61,33 @@ function stripAlphaChannel(color: string): string {
     return color;
 }
 
+// Push an entry to the clipboard, asking the user for the inputs of a template
+async function pushToClipboard(id: number) {
+  try {
+    await invoke("push_to_clipboard", { id });
+  } catch (error) {
+    const appError = error as AppError;
+    if (appError?.kind !== "input_required" || !appError.inputs) {
+      throw error;
+    }
+
+    const inputs: Record<string, string> = {};
+    for (const name of appError.inputs) {
+      const value = prompt(name);
+      if (value === null) {
+        return;
+      }
+      inputs[name] = value;
+    }
+    await invoke("push_to_clipboard", { id, inputs });
+  }
+}
+
 export const ClipboardItem = component$<ClipboardItemProps>(({ orientation, entry }) => {
   const overlayClass = useSignal("");
   const itemRef = useSignal<HTMLElement>();
   const language = useSignal("Raw text");
+  const expiresAt = useSignal(entry.expires_at);
 
 
   // Listen for the unpin-all event and unpin all items
@@ -103,7 +137,7 @@ export const ClipboardItem = component$<ClipboardItemProps>(({ orientation, entr
         (isDeleting.value ? " shrink-out" : "") */
       }
       onClick$={(e, target) => {
-        invoke("push_to_clipboard", { id: entry.id });
+        pushToClipboard(entry.id).catch(showError);
 
         const overlay = target?.querySelector(".click-overlay") as HTMLElement;
         const circle = overlay?.querySelector(".checkmark-circle");
@@ -147,8 +181,11 @@ export const ClipboardItem = component$<ClipboardItemProps>(({ orientation, entr
           <div class="clipboard-item-color-text">{entry.content}</div>
         </div>
       ) : (
-        <div class="clipboard-history-item-content">
-          {language.value === "Raw text" ? (
+        <div class="clipboard-history-item-content" title={entry.note ?? undefined}>
+          {entry.title ? (
+            // The preview holds the title instead of the content
+            <p class="clipboard-item-title">{entry.content}</p>
+          ) : language.value === "Raw text" ? (
             entry.content.split(/\r?\n/).map((line, i) => (
               <p key={i} class="clipboard-item-content-line">{line}</p>
             ))
@@ -172,7 +209,7 @@ export const ClipboardItem = component$<ClipboardItemProps>(({ orientation, entr
               itemRef.value?.classList.remove("expand-in");
               itemRef.value?.classList.add("shrink-out");
               setTimeout(() => {
-                invoke("delete_item", { id: entry.id });
+                invoke("delete_item", { id: entry.id }).catch(showError);
               }, 350);
             }}
             class="overlay-button"
@@ -188,7 +225,7 @@ export const ClipboardItem = component$<ClipboardItemProps>(({ orientation, entr
               onClick$={(e) => e.stopPropagation()}
               onChange$={async (e) => {
                 const selectedLanguage = (e.target as HTMLSelectElement).value;
-                invoke("force_language", { id: entry.id, language: selectedLanguage });
+                invoke("force_language", { id: entry.id, language: selectedLanguage }).catch(showError);
               }}
             >
               <option value="Raw text">Raw text</option>
@@ -207,7 +244,30 @@ export const ClipboardItem = component$<ClipboardItemProps>(({ orientation, entr
       </div>
         <div class="row-wrapper lower-wrapper">
           <div class="lower-row">
-            <div class="date">{entry.added_at}</div>
+            <div class="date">
+              {entry.added_at}
+              {entry.source && (
+                <span class="source" title={entry.source.title ?? ""}>
+                  {entry.source.class ?? entry.source.process}
+                </span>
+              )}
+              {expiresAt.value && (
+                <span class="expiry" title={"Deleted at " + expiresAt.value + " (UTC)"}>
+                  ⏳
+                </span>
+              )}
+              {entry.sensitive && (
+                <span
+                  class="sensitive"
+                  title={expiresAt.value ? "Deleted at " + expiresAt.value + " (UTC)" : "Masked"}
+                >
+                  {entry.sensitive.replace("_", " ")}
+                </span>
+              )}
+              {entry.tags.length > 0 && (
+                <span class="tags">{entry.tags.map((tag) => "#" + tag).join(" ")}</span>
+              )}
+            </div>
               <div class="lower-row-buttons">
                 { entry.entry_type === "url" &&
                   <button
@@ -234,7 +294,32 @@ export const ClipboardItem = component$<ClipboardItemProps>(({ orientation, entr
                 <button
                   onClick$={async (e) => {
                     e.stopPropagation();
-                    const res = await invoke("toggle_pin", { id: entry.id, state: !entry.pinned });
+                    // An empty answer keeps the entry until it is deleted
+                    const answer = prompt("Delete this entry after how many minutes? (empty to keep it)", "");
+                    if (answer === null) {
+                      return;
+                    }
+                    const minutes = answer.trim() === "" ? null : parseInt(answer, 10);
+                    if (Number.isNaN(minutes)) {
+                      return;
+                    }
+                    await invoke<string | null>("set_entry_expiry", { id: entry.id, minutes })
+                      .then((date) => {
+                        expiresAt.value = date;
+                      })
+                      .catch(showError);
+                  }}
+                  class="overlay-button expiry-button"
+                >
+                  <IconHover regular="clock" solid="clock" class="expiry-button" />
+                </button>
+                <button
+                  onClick$={async (e) => {
+                    e.stopPropagation();
+                    const res = await invoke("toggle_pin", { id: entry.id, state: !entry.pinned }).catch((error) => {
+                      showError(error);
+                      return false;
+                    });
                     itemRef.value?.classList.remove("expand-in");
                     itemRef.value?.classList.add("shrink-out");
                     if (res) {
@@ -244,7 +329,7 @@ export const ClipboardItem = component$<ClipboardItemProps>(({ orientation, entr
                         itemRef.value?.classList.add("expand-in");
                       }, 350);
                     } else {
-                      alert("Error pinning item");
+                      console.error("Error pinning item");
                       itemRef.value?.classList.remove("shrink-out");
                       itemRef.value?.classList.add("expand-in");
                     }
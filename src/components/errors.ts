@@ -0,0 +1,18 @@
+// Error returned by a backend command (see `AppError` in src-tauri/src/core/errors.rs)
+export type AppError = {
+  kind: string;
+  message: string;
+  position?: number; // "template": index of the faulty placeholder
+  inputs?: string[]; // "input_required": names of the inputs of the template
+};
+
+// Show an error returned by a backend command to the user
+export function showError(error: unknown) {
+  console.error(error);
+
+  const message =
+    typeof error === "object" && error !== null && "message" in error
+      ? (error as AppError).message
+      : String(error);
+  alert(message);
+}
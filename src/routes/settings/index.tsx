@@ -5,6 +5,7 @@ import { WebviewWindow } from '@tauri-apps/api/webviewWindow';
 import { Tabs, Button, Tooltip, Spinner } from 'flowbite-qwik';
 import { platform } from '@tauri-apps/plugin-os';
 import { enable as enable_autostart, disable as disable_autostart, isEnabled as is_autostart_enabled } from '@tauri-apps/plugin-autostart';
+import { showError } from '../../components/errors';
 
 
 export const SettingsPage = component$(() => {
@@ -460,7 +461,7 @@ export const SettingsPage = component$(() => {
               class="w-32 h-10"
               onClick$={
                 $(async () => {
-                  invoke("reset_config")
+                  invoke("reset_config").catch(showError)
                 })
               }
             >Reset to defaults</Button>
@@ -490,7 +491,7 @@ export const SettingsPage = component$(() => {
                 class="w-32 h-10"
                 onClick$={
                   $(async () => {
-                    invoke("preview_config", { payload: JSON.stringify(config.value) });
+                    invoke("preview_config", { payload: JSON.stringify(config.value) }).catch(showError);
                   })
                 }
               >Preview</Button>
@@ -518,7 +519,7 @@ export const SettingsPage = component$(() => {
                     }
                     
 
-                      invoke("save_config", { payload: JSON.stringify(config.value) });
+                      invoke("save_config", { payload: JSON.stringify(config.value) }).catch(showError);
                   })
                 }
               >Save</Button>